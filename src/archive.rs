@@ -0,0 +1,198 @@
+use std::io::{self, BufRead, BufReader, Read};
+
+use chrono::{DateTime, Utc};
+use flate2::read::MultiGzDecoder;
+
+use crate::{
+    DiprError, DiprHeader, ParseOptions, PrecipRate, Wrapper, header::parse_unwrapped_header,
+    parse_unwrapped, wrapper::unwrap_input,
+};
+
+/// Reader for tar archives of many products, like the Level III archives from NCEI
+///
+/// The archive may be gzip-compressed, which is detected automatically. Each product in it may
+/// also be wrapped as described in [`Wrapper`].
+///
+/// ```no_run
+/// use std::{fs::File, io::BufReader};
+///
+/// use dipr::{ArchiveFilter, DiprArchive, ParseOptions};
+///
+/// # fn main() -> Result<(), dipr::DiprError> {
+/// let filter = ArchiveFilter {
+///     stations: vec!["KOAX".to_string()],
+///     ..Default::default()
+/// };
+/// let file = BufReader::new(File::open("HAS012345678.tar.gz")?);
+/// let mut archive = DiprArchive::with_options(file, &ParseOptions::default(), filter)?;
+/// for (name, dipr) in archive.products()? {
+///     match dipr {
+///         Ok(dipr) => println!("{name}: {}", dipr.capture_time),
+///         Err(e) => eprintln!("{name}: {e}"),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct DiprArchive<R: Read> {
+    archive: tar::Archive<ArchiveSource<R>>,
+    options: ParseOptions,
+    filter: ArchiveFilter,
+}
+
+/// Criteria that decide which products [`DiprArchive::products`] yields
+///
+/// Every criterion is checked against the headers of each product, not against its name in the
+/// archive. The [`Default`] implementation accepts every product.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct ArchiveFilter {
+    /// Radar stations to accept, compared case-insensitively with
+    /// [`PrecipRate::station_code`], or empty to accept every station
+    pub stations: Vec<String>,
+    /// Product codes to accept, compared with
+    /// [`ProductDescription::product_code`](crate::ProductDescription::product_code), or empty to
+    /// accept every product code
+    pub product_codes: Vec<i16>,
+    /// Earliest capture time to accept, inclusive
+    pub start: Option<DateTime<Utc>>,
+    /// Latest capture time to accept, exclusive
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl ArchiveFilter {
+    /// Whether the product with `header` should be yielded
+    ///
+    /// `header` must include the capture time if [`ArchiveFilter::has_time_range`] is true.
+    fn accepts(&self, header: &DiprHeader) -> bool {
+        let station = self.stations.is_empty()
+            || self
+                .stations
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(&header.station_code));
+        let product_code = self.product_codes.is_empty()
+            || self
+                .product_codes
+                .contains(&header.product_description.product_code);
+        let time = match header.capture_time {
+            Some(t) => {
+                self.start.is_none_or(|start| t >= start) && self.end.is_none_or(|end| t < end)
+            }
+            None => !self.has_time_range(),
+        };
+        station && product_code && time
+    }
+
+    fn has_time_range(&self) -> bool {
+        self.start.is_some() || self.end.is_some()
+    }
+}
+
+impl<R: Read> DiprArchive<R> {
+    /// Open a tar archive using the default [`ParseOptions`] and accepting every product
+    pub fn new(reader: R) -> Result<Self, DiprError> {
+        Self::with_options(reader, &ParseOptions::default(), ArchiveFilter::default())
+    }
+
+    /// Open a tar archive whose products are parsed according to `options` and selected by
+    /// `filter`
+    ///
+    /// This only reads far enough to tell whether the archive is gzip-compressed.
+    pub fn with_options(
+        reader: R,
+        options: &ParseOptions,
+        filter: ArchiveFilter,
+    ) -> Result<Self, DiprError> {
+        let mut reader = BufReader::new(reader);
+        let source = if reader.fill_buf()?.starts_with(Wrapper::GZIP_MAGIC) {
+            ArchiveSource::Gzip(Box::new(MultiGzDecoder::new(reader)))
+        } else {
+            ArchiveSource::Plain(reader)
+        };
+        Ok(DiprArchive {
+            archive: tar::Archive::new(source),
+            options: options.clone(),
+            filter,
+        })
+    }
+
+    /// Iterate over the products in the archive, giving each of their entry names and parse
+    /// results in a tuple
+    ///
+    /// Entries that aren't regular files and products that the [`ArchiveFilter`] rejects are
+    /// skipped. A product whose headers can't be parsed can't be filtered, so it's always yielded
+    /// with its error. If the archive itself turns out to be corrupt, the last item has the error
+    /// and the name of the entry that was being read, or an empty name if there was none.
+    ///
+    /// Entries are read in order, so this can only be called once.
+    pub fn products(
+        &mut self,
+    ) -> Result<impl Iterator<Item = (String, Result<PrecipRate, DiprError>)> + '_, DiprError> {
+        let options = &self.options;
+        let filter = &self.filter;
+        let mut entries = self.archive.entries()?;
+        let mut failed = false;
+        Ok(std::iter::from_fn(move || {
+            while !failed {
+                let mut entry = match entries.next()? {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        failed = true;
+                        return Some((String::new(), Err(e.into())));
+                    }
+                };
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry.path().map_or_else(
+                    |_| String::from_utf8_lossy(&entry.path_bytes()).into_owned(),
+                    |p| p.to_string_lossy().into_owned(),
+                );
+                let input = match read_entry(&mut entry, options.max_uncompressed_size) {
+                    Ok(input) => input,
+                    Err(e) => {
+                        failed = matches!(e, DiprError::Io(_));
+                        return Some((name, Err(e)));
+                    }
+                };
+                // remove any containers once, then read the headers before parsing the rest
+                let (input, wrappers) = match unwrap_input(&input, options.max_uncompressed_size) {
+                    Ok(unwrapped) => unwrapped,
+                    Err(e) => return Some((name, Err(e))),
+                };
+                let peek = filter.has_time_range();
+                match parse_unwrapped_header(&input, wrappers.clone(), options, peek) {
+                    Ok(header) if !filter.accepts(&header) => continue,
+                    Ok(_) => return Some((name, parse_unwrapped(&input, wrappers, options))),
+                    Err(e) => return Some((name, Err(e))),
+                }
+            }
+            None
+        }))
+    }
+}
+
+/// Read a whole entry into memory, as long as it's no larger than `limit`
+fn read_entry(entry: &mut impl Read, limit: usize) -> Result<Vec<u8>, DiprError> {
+    // read one byte past the limit so that we can tell whether it was exceeded
+    let mut input = vec![];
+    entry.take(limit as u64 + 1).read_to_end(&mut input)?;
+    if input.len() > limit {
+        return Err(DiprError::PayloadTooLarge { limit });
+    }
+    Ok(input)
+}
+
+/// Tar archive stream, which may be gzip-compressed
+enum ArchiveSource<R: Read> {
+    Plain(BufReader<R>),
+    Gzip(Box<MultiGzDecoder<BufReader<R>>>),
+}
+
+impl<R: Read> Read for ArchiveSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ArchiveSource::Plain(reader) => reader.read(buf),
+            ArchiveSource::Gzip(decoder) => decoder.read(buf),
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use uom::si::{f32::Length, length::meter};
+
+use crate::{
+    DiprError, ParseResult, ProductType,
+    parameters::{Parameter, encode_parameter_list, parameter_list},
+    radials::{Radial, encode_radial, radial},
+    utils::*,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+/// One component of a generic product's symbology block
+pub enum Component {
+    /// Data organized by azimuth and range (Figure E-3)
+    Radial(RadialComponent),
+    /// Point, polygon, or polyline (Figure E-5)
+    Area(AreaComponent),
+    /// Free-form text (Figure E-7)
+    Text(TextComponent),
+}
+
+impl Component {
+    pub(crate) const NAME: &'static str = "component";
+    pub(crate) const RADIAL_TYPE: i32 = 1;
+    /// Non-null XDR pointer that precedes each component in the product symbology block
+    pub(crate) const POINTER_MARKER: i32 = 1;
+    const AREA_TYPE: i32 = 3;
+    const TEXT_TYPE: i32 = 4;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Radial Component Data Structure (Figure E-3)
+pub struct RadialComponent {
+    /// Description of the data in this component
+    pub description: String,
+    /// Distance between the inner and outer extents of each bin measured radially
+    pub bin_size: Length,
+    /// Distance between the radar station and the center of the nearest bin
+    pub range_to_first_bin: Length,
+    /// Component parameters keyed by [`Parameter::id`]
+    pub parameters: BTreeMap<String, Parameter>,
+    /// Data in this component organized by azimuth
+    pub radials: Vec<Radial>,
+}
+
+impl RadialComponent {
+    pub(crate) const NAME: &'static str = "radial component";
+    pub(crate) const BIN_SIZE_RANGE: RangeInclusive<f32> = (0.)..=1000.;
+    // The specification gives a lower bound of 1000 m, but actual DIPR files put the first bin
+    // closer to the radar than that, so only the sign is checked at the low end
+    pub(crate) const RANGE_TO_FIRST_BIN_RANGE: RangeInclusive<f32> = (0.)..=460000.;
+    pub(crate) const NUM_RADIALS_RANGE: RangeInclusive<i32> = 0..=800;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Area Component Data Structure (Figure E-5)
+pub struct AreaComponent {
+    /// Component parameters keyed by [`Parameter::id`]
+    pub parameters: BTreeMap<String, Parameter>,
+    /// Raw area type, which encodes both the kind of geometry and how its points are located
+    pub area_type: i32,
+    /// Vertices of the area as pairs of floats whose meaning depends on
+    /// [`AreaComponent::area_type`], e.g., latitude/longitude or azimuth/range
+    pub points: Vec<(f32, f32)>,
+}
+
+impl AreaComponent {
+    pub(crate) const NAME: &'static str = "area component";
+    const NUM_POINTS_RANGE: RangeInclusive<i32> = 0..=100_000;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Text Component Data Structure (Figure E-7)
+pub struct TextComponent {
+    /// Component parameters keyed by [`Parameter::id`]
+    pub parameters: BTreeMap<String, Parameter>,
+    /// Text carried by this component
+    pub text: String,
+}
+
+impl TextComponent {
+    pub(crate) const NAME: &'static str = "text component";
+}
+
+/// Parse a component of a product of type `product_type`, dispatching on its type
+pub(crate) fn component<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+    product_type: ProductType,
+) -> ParseResult<'a, Component> {
+    let (component_type, tail) = take_i32(input)?;
+    let in_section = |section| {
+        move |e: DiprError| {
+            e.with_context(|c| {
+                if c.section.is_empty() {
+                    c.section = section;
+                }
+            })
+        }
+    };
+    match component_type {
+        Component::RADIAL_TYPE => radial_component(tail, validator, product_type)
+            .map(|(c, tail)| (Component::Radial(c), tail))
+            .map_err(in_section(RadialComponent::NAME)),
+        Component::AREA_TYPE => area_component(tail, validator)
+            .map(|(c, tail)| (Component::Area(c), tail))
+            .map_err(in_section(AreaComponent::NAME)),
+        Component::TEXT_TYPE => text_component(tail, validator)
+            .map(|(c, tail)| (Component::Text(c), tail))
+            .map_err(in_section(TextComponent::NAME)),
+        t => Err(DiprError::Unsupported(format!(
+            "found unsupported component type {t} in product symbology; only radial ({}), area ({}), and text ({}) components are supported",
+            Component::RADIAL_TYPE,
+            Component::AREA_TYPE,
+            Component::TEXT_TYPE
+        ))
+        .with_context(|c| {
+            c.section = Component::NAME;
+            c.field = Some("component type");
+            c.remaining = Some(input.len());
+        })),
+    }
+}
+
+/// Parse Radial Component Data Structure (Figure E-3), starting after the component type
+pub(crate) fn radial_component<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+    product_type: ProductType,
+) -> ParseResult<'a, RadialComponent> {
+    let ((mut component, num_radials), mut tail) = radial_component_header(input, validator)?;
+
+    // parse the radials themselves
+    component.radials.reserve_exact(num_radials);
+    for radial_idx in 0..num_radials {
+        let tmp = validator.in_radial(radial_idx, |v| radial(tail, v, product_type))?;
+        component.radials.push(tmp.0);
+        tail = tmp.1;
+    }
+
+    Ok((component, tail))
+}
+
+/// Parse everything in a radial component up to the radials themselves
+///
+/// This returns the component with no radials, along with the number of radials that follow.
+pub(crate) fn radial_component_header<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, (RadialComponent, usize)> {
+    let (description, tail) = take_string(input)?;
+    let (bin_size, tail) = take_float(tail)?;
+    validator.check_range_inclusive(
+        RadialComponent::BIN_SIZE_RANGE,
+        bin_size,
+        "bin size",
+        RadialComponent::NAME,
+        tail,
+    )?;
+    let (range_to_first_bin, tail) = take_float(tail)?;
+    validator.check_range_inclusive(
+        RadialComponent::RANGE_TO_FIRST_BIN_RANGE,
+        range_to_first_bin,
+        "range to first bin",
+        RadialComponent::NAME,
+        tail,
+    )?;
+    let (parameters, tail) = parameter_list(tail, validator)?;
+    let (num_radials, tail) = take_i32(tail)?;
+    validator.check_range_inclusive(
+        RadialComponent::NUM_RADIALS_RANGE,
+        num_radials,
+        "num radials",
+        RadialComponent::NAME,
+        tail,
+    )?;
+    let num_radials = validator.check_count(
+        validator.options().max_radials,
+        num_radials,
+        "num radials",
+        RadialComponent::NAME,
+        tail,
+    )?;
+
+    Ok((
+        (
+            RadialComponent {
+                description,
+                bin_size: Length::new::<meter>(bin_size),
+                range_to_first_bin: Length::new::<meter>(range_to_first_bin),
+                parameters,
+                radials: vec![],
+            },
+            num_radials,
+        ),
+        tail,
+    ))
+}
+
+/// Record that `e` happened while parsing the radial at `radial_idx`
+pub(crate) fn in_radial(e: DiprError, radial_idx: usize) -> DiprError {
+    e.with_context(|c| {
+        if c.section.is_empty() {
+            c.section = Radial::NAME;
+        }
+        c.radial = Some(radial_idx);
+    })
+}
+
+/// Parse Area Component Data Structure (Figure E-5), starting after the component type
+fn area_component<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, AreaComponent> {
+    let (parameters, tail) = parameter_list(input, validator)?;
+    let (area_type, tail) = take_i32(tail)?;
+    let (num_points, tail) = take_i32(tail)?;
+    validator.check_range_inclusive(
+        AreaComponent::NUM_POINTS_RANGE,
+        num_points,
+        "num points",
+        AreaComponent::NAME,
+        tail,
+    )?;
+    // the number of points is repeated as the length of the XDR array that holds them
+    let (_array_length, mut tail) = take_i32(tail)?;
+    // a lenient parse may have let through a bogus count, so don't trust it for allocation
+    let mut points = Vec::with_capacity((num_points.max(0) as usize).min(tail.len() / 8));
+    for _ in 0..num_points {
+        let (a, t) = take_float(tail)?;
+        let (b, t) = take_float(t)?;
+        points.push((a, b));
+        tail = t;
+    }
+    Ok((
+        AreaComponent {
+            parameters,
+            area_type,
+            points,
+        },
+        tail,
+    ))
+}
+
+/// Parse Text Component Data Structure (Figure E-7), starting after the component type
+fn text_component<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, TextComponent> {
+    let (parameters, tail) = parameter_list(input, validator)?;
+    let (text, tail) = take_string(tail)?;
+    Ok((TextComponent { parameters, text }, tail))
+}
+
+/// Encode a component of a product of type `product_type`, including its type
+pub(crate) fn encode_component(
+    component: &Component,
+    product_type: ProductType,
+    out: &mut Vec<u8>,
+) -> Result<(), DiprError> {
+    match component {
+        Component::Radial(c) => {
+            put_i32(out, Component::RADIAL_TYPE);
+            encode_radial_component(c, &c.radials, product_type, out)?;
+        }
+        Component::Area(c) => {
+            put_i32(out, Component::AREA_TYPE);
+            encode_parameter_list(&c.parameters, out);
+            put_i32(out, c.area_type);
+            put_i32(out, c.points.len() as i32);
+            put_i32(out, c.points.len() as i32);
+            for (a, b) in &c.points {
+                put_float(out, *a);
+                put_float(out, *b);
+            }
+        }
+        Component::Text(c) => {
+            put_i32(out, Component::TEXT_TYPE);
+            encode_parameter_list(&c.parameters, out);
+            put_string(out, &c.text);
+        }
+    }
+    Ok(())
+}
+
+/// Encode Radial Component Data Structure (Figure E-3), starting after the component type
+///
+/// The radials are taken from `radials` instead of [`RadialComponent::radials`].
+pub(crate) fn encode_radial_component(
+    component: &RadialComponent,
+    radials: &[Radial],
+    product_type: ProductType,
+    out: &mut Vec<u8>,
+) -> Result<(), DiprError> {
+    put_string(out, &component.description);
+    put_float(out, component.bin_size.get::<meter>());
+    put_float(out, component.range_to_first_bin.get::<meter>());
+    encode_parameter_list(&component.parameters, out);
+    put_i32(out, radials.len() as i32);
+    for radial in radials {
+        encode_radial(radial, product_type, out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    /// Parameter list holding a single `color` parameter
+    fn put_parameters(out: &mut Vec<u8>) {
+        put_i32(out, 1);
+        put_i32(out, 1);
+        put_string(out, "color");
+        put_string(out, "value=red;");
+    }
+
+    /// Area component with a triangle of latitude/longitude points
+    fn area() -> Vec<u8> {
+        let mut out = vec![];
+        put_i32(&mut out, Component::AREA_TYPE);
+        put_parameters(&mut out);
+        put_i32(&mut out, 2);
+        put_i32(&mut out, 3);
+        put_i32(&mut out, 3);
+        for (a, b) in [(41.25, -96.5), (41.5, -96.25), (41.75, -96.5)] {
+            put_float(&mut out, a);
+            put_float(&mut out, b);
+        }
+        out
+    }
+
+    fn parse(input: &[u8]) -> Result<Component, DiprError> {
+        let mut validator = Validator::new(&ParseOptions::default());
+        let (component, tail) = component(input, &mut validator, ProductType::PrecipRate)?;
+        assert!(tail.is_empty());
+        Ok(component)
+    }
+
+    #[test]
+    fn area_components_are_decoded() {
+        let input = area();
+        let Component::Area(c) = parse(&input).unwrap() else {
+            panic!("expected an area component");
+        };
+        assert_eq!(c.parameters["color"].raw_attributes, "value=red;");
+        assert_eq!(c.area_type, 2);
+        assert_eq!(c.points, [(41.25, -96.5), (41.5, -96.25), (41.75, -96.5)]);
+
+        let mut out = vec![];
+        encode_component(&Component::Area(c), ProductType::PrecipRate, &mut out).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn text_components_are_decoded() {
+        let mut input = vec![];
+        put_i32(&mut input, Component::TEXT_TYPE);
+        put_parameters(&mut input);
+        put_string(&mut input, "no precipitation detected");
+        let Component::Text(c) = parse(&input).unwrap() else {
+            panic!("expected a text component");
+        };
+        assert_eq!(c.parameters["color"].attributes["value"], "red");
+        assert_eq!(c.text, "no precipitation detected");
+
+        let mut out = vec![];
+        encode_component(&Component::Text(c), ProductType::PrecipRate, &mut out).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn truncated_points_are_blamed_on_the_area_component() {
+        let mut input = area();
+        input.truncate(input.len() - 2);
+        let e = parse(&input).unwrap_err();
+        assert_eq!(e.context().unwrap().section, AreaComponent::NAME);
+        assert!(
+            matches!(e.without_context(), DiprError::Truncated { .. }),
+            "{e:?}"
+        );
+    }
+
+    #[test]
+    fn unknown_component_types_are_unsupported() {
+        let mut input = area();
+        input[..4].copy_from_slice(&2i32.to_be_bytes());
+        let e = parse(&input).unwrap_err();
+        let context = e.context().unwrap();
+        assert_eq!(context.field, Some("component type"));
+        assert_eq!(context.remaining, Some(input.len()));
+        assert!(
+            matches!(e.without_context(), DiprError::Unsupported(_)),
+            "{e:?}"
+        );
+    }
+}
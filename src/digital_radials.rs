@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use uom::si::{
+    angle::degree,
+    f32::{Angle, Length},
+    length::meter,
+};
+
+use crate::{ParseResult, ProductType, Quantity, Radial, Thresholds, utils::*};
+
+/// Digital Radial Data Array Packet (packet code 16) with its data levels decoded
+pub(crate) struct DigitalRadials {
+    pub(crate) range_to_first_bin: Length,
+    pub(crate) bin_size: Length,
+    pub(crate) radials: Vec<Radial>,
+}
+
+impl DigitalRadials {
+    pub(crate) const NAME: &'static str = "digital radial data array";
+    pub(crate) const PACKET_CODE: i16 = 16;
+    const FIRST_BIN_RANGE: RangeInclusive<i16> = 0..=920;
+    /// The accumulation products have 920 bins, and the others have at most 460
+    const NUM_BINS_RANGE: RangeInclusive<i16> = 0..=920;
+    const NUM_RADIALS_RANGE: RangeInclusive<i16> = 0..=720;
+    /// Range of the start angle in tenths of a degree
+    const START_ANGLE_RANGE: RangeInclusive<i16> = 0..=3600;
+    /// Range of the angle delta in tenths of a degree
+    const ANGLE_DELTA_RANGE: RangeInclusive<i16> = 0..=20;
+
+    /// Size of the bins in a product of type `product_type` (Table V)
+    ///
+    /// The packet has a range scale factor, but it's only meant for scaling the display.
+    fn bin_size_meters(product_type: ProductType) -> f32 {
+        match product_type.quantity() {
+            Quantity::Accumulation => 250.,
+            _ => 1000.,
+        }
+    }
+}
+
+/// Parse Digital Radial Data Array Packet from a product of type `product_type`, converting data
+/// levels into values with `thresholds`
+///
+/// Every radial is given `elevation`, which the packet doesn't store.
+pub(crate) fn digital_radials<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+    product_type: ProductType,
+    thresholds: &Thresholds,
+    elevation: Angle,
+) -> ParseResult<'a, DigitalRadials> {
+    let (packet_code, tail) = take_i16(input)?;
+    check_value(
+        DigitalRadials::PACKET_CODE,
+        packet_code,
+        "packet code",
+        DigitalRadials::NAME,
+        tail,
+    )?;
+
+    let (first_bin, tail) = take_i16(tail)?;
+    validator.check_range_inclusive(
+        DigitalRadials::FIRST_BIN_RANGE,
+        first_bin,
+        "index of first range bin",
+        DigitalRadials::NAME,
+        tail,
+    )?;
+
+    let (num_bins, tail) = take_i16(tail)?;
+    validator.check_range_inclusive(
+        DigitalRadials::NUM_BINS_RANGE,
+        num_bins,
+        "number of range bins",
+        DigitalRadials::NAME,
+        tail,
+    )?;
+    let num_bins = validator.check_count(
+        validator.options().max_bins,
+        num_bins.into(),
+        "number of range bins",
+        DigitalRadials::NAME,
+        tail,
+    )?;
+
+    // the display center and range scale factor don't affect where the bins are
+    let (_i_center, tail) = take_i16(tail)?;
+    let (_j_center, tail) = take_i16(tail)?;
+    let (_range_scale_factor, tail) = take_i16(tail)?;
+
+    let (num_radials, mut tail) = take_i16(tail)?;
+    validator.check_range_inclusive(
+        DigitalRadials::NUM_RADIALS_RANGE,
+        num_radials,
+        "number of radials",
+        DigitalRadials::NAME,
+        tail,
+    )?;
+    let num_radials = validator.check_count(
+        validator.options().max_radials,
+        num_radials.into(),
+        "number of radials",
+        DigitalRadials::NAME,
+        tail,
+    )?;
+
+    let quantity = product_type.quantity();
+    let mut radials = Vec::with_capacity(num_radials);
+    for radial_idx in 0..num_radials {
+        let (radial, t) = validator.in_radial(radial_idx, |v| {
+            let (num_bytes, t) = take_i16(tail)?;
+            v.check_range_inclusive(
+                (num_bins as i16)..=i16::MAX,
+                num_bytes,
+                "number of bytes",
+                DigitalRadials::NAME,
+                t,
+            )?;
+            let (start_angle, t) = take_i16(t)?;
+            v.check_range_inclusive(
+                DigitalRadials::START_ANGLE_RANGE,
+                start_angle,
+                "radial start angle",
+                DigitalRadials::NAME,
+                t,
+            )?;
+            let (angle_delta, t) = take_i16(t)?;
+            v.check_range_inclusive(
+                DigitalRadials::ANGLE_DELTA_RANGE,
+                angle_delta,
+                "radial angle delta",
+                DigitalRadials::NAME,
+                t,
+            )?;
+            let (levels, t) = take_bytes(t, num_bytes.max(0) as usize).map_err(|e| {
+                e.with_context(|c| {
+                    c.section = DigitalRadials::NAME;
+                    c.field = Some("bins");
+                    c.bin = Some(t.len());
+                })
+            })?;
+            // a lenient parse may have let through a short radial, so only keep the bins it has
+            let levels = &levels[..num_bins.min(levels.len())];
+
+            // the start angle is the leading edge of the radial, but its azimuth is the center
+            let width = angle_delta as f32 / 10.;
+            let azimuth = (start_angle as f32 / 10. + width / 2.) % 360.;
+            Ok((
+                Radial {
+                    azimuth: Angle::new::<degree>(azimuth),
+                    elevation,
+                    width: Angle::new::<degree>(width),
+                    values: levels
+                        .iter()
+                        .map(|level| quantity.measurement(thresholds.decode(*level)))
+                        .collect(),
+                    raw_values: levels.iter().map(|level| *level as u32).collect(),
+                    raw_attributes: String::new(),
+                    attributes: BTreeMap::new(),
+                },
+                t,
+            ))
+        })?;
+        radials.push(radial);
+        tail = t;
+    }
+
+    let bin_size = Length::new::<meter>(DigitalRadials::bin_size_meters(product_type));
+    Ok((
+        DigitalRadials {
+            range_to_first_bin: bin_size * (first_bin as f32 + 0.5),
+            bin_size,
+            radials,
+        },
+        tail,
+    ))
+}
@@ -6,7 +6,10 @@ use std::{
     string::FromUtf8Error,
 };
 
+use crate::Wrapper;
+
 #[derive(Debug)]
+#[non_exhaustive]
 /// Indicates a product-specific parsing error or wraps a lower-level error
 pub enum DiprError {
     /// Found an invalid value for the station's operational mode
@@ -15,8 +18,42 @@ pub enum DiprError {
     ///
     /// Since this value is defined as a Unix timestamp, this error variant should be unreachable.
     InvalidCaptureTime(u32),
-    /// Failed to decompress the symbology block using [`bzip2_rs`]
+    /// Failed to read input or write output
+    Io(io::Error),
+    /// Failed to decompress the symbology block using [`bzip2`]
+    ///
+    /// [`bzip2`] reports corrupt streams as [`io::Error`]s, but this variant only ever wraps
+    /// errors from the decompressor. Other I/O errors use [`DiprError::Io`].
     DecompressionFailed(io::Error),
+    /// Decompressed symbology block or gzip-wrapped product grew larger than
+    /// [`ParseOptions::max_uncompressed_size`](crate::ParseOptions::max_uncompressed_size)
+    PayloadTooLarge {
+        /// Limit that was exceeded, in bytes
+        limit: usize,
+    },
+    /// Parsed count was larger than the limit set in [`ParseOptions`](crate::ParseOptions)
+    LimitExceeded {
+        /// Name of the count, e.g., `"num radials"`
+        name: &'static str,
+        /// Limit that was exceeded
+        limit: usize,
+        /// Count that was parsed
+        actual: usize,
+    },
+    /// Failed to remove a container from around the product
+    UnwrapFailed {
+        /// Container that couldn't be removed
+        wrapper: Wrapper,
+        /// What went wrong
+        source: io::Error,
+    },
+    /// Decompressed symbology block was a different size than the product description advertised
+    UncompressedSizeMismatch {
+        /// Size given in the product description block
+        expected: u32,
+        /// Size of the decompressed payload
+        actual: usize,
+    },
     /// Failed to convert a byte slice to a [`String`] due to invalid UTF-8
     InvalidUtf8String(FromUtf8Error),
     /// Failed to convert a byte slice to a fixed-length array
@@ -25,6 +62,180 @@ pub enum DiprError {
     ValueOutOfRange(String),
     /// Encountered a DIPR file variant that this crate doesn't support
     Unsupported(String),
+    /// Value can't be represented in the DIPR format, so the product can't be encoded
+    Unencodable(String),
+    /// Input ended before a value could be parsed
+    ///
+    /// This usually means that the file is incomplete, e.g., because it was read while still being
+    /// written.
+    Truncated {
+        /// Section of the product that was being parsed
+        section: &'static str,
+        /// Position of the value in the stream that contains `section`
+        ///
+        /// For sections inside the product symbology block, this is an offset into the
+        /// decompressed payload. Otherwise, it's an offset into the original input.
+        offset: usize,
+        /// Number of bytes needed to parse the value
+        needed: usize,
+        /// Number of bytes remaining in the stream
+        available: usize,
+    },
+    /// Wraps another error with the location in the product where it occurred
+    ///
+    /// [`parse_dipr`](crate::parse_dipr) wraps every parsing error in exactly one of these. Use
+    /// [`DiprError::without_context`] to get at the underlying error.
+    Context {
+        /// Where the error occurred
+        context: ErrorContext,
+        /// What went wrong
+        source: Box<DiprError>,
+    },
+}
+
+/// Byte stream that an [`ErrorContext`] offset refers to
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Stream {
+    /// The product as it was passed in, with the symbology block still compressed
+    ///
+    /// If the product was wrapped in a container like gzip, this is the product after the
+    /// container was removed.
+    Input,
+    /// The product symbology block after decompression
+    Decompressed,
+}
+
+impl Display for Stream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stream::Input => write!(f, "input"),
+            Stream::Decompressed => write!(f, "decompressed product symbology"),
+        }
+    }
+}
+
+/// Location in a DIPR product where an error occurred
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct ErrorContext {
+    /// Stream that `offset` refers to
+    pub stream: Stream,
+    /// Position of the offending value from the start of `stream`, if known
+    pub offset: Option<usize>,
+    /// Section of the product that was being parsed
+    pub section: &'static str,
+    /// Name of the value that was being parsed, if known
+    pub field: Option<&'static str>,
+    /// Zero-based index of the radial that was being parsed, if any
+    pub radial: Option<usize>,
+    /// Zero-based index of the bin within `radial` that was being parsed, if any
+    pub bin: Option<usize>,
+    /// Bytes left in the stream at the offending value, used to compute `offset`
+    pub(crate) remaining: Option<usize>,
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.section)?;
+        if let Some(field) = self.field {
+            write!(f, ", field {field}")?;
+        }
+        if let Some(radial) = self.radial {
+            write!(f, ", radial {radial}")?;
+        }
+        if let Some(bin) = self.bin {
+            write!(f, ", bin {bin}")?;
+        }
+        match self.offset {
+            Some(offset) => write!(f, " at byte {offset} of the {} stream", self.stream),
+            None => write!(f, " in the {} stream", self.stream),
+        }
+    }
+}
+
+impl DiprError {
+    /// Location in the product where this error occurred, if known
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            DiprError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Underlying error with any [`DiprError::Context`] removed
+    ///
+    /// This is useful for matching on the kind of error without caring where it happened.
+    pub fn without_context(&self) -> &DiprError {
+        match self {
+            DiprError::Context { source, .. } => source.without_context(),
+            e => e,
+        }
+    }
+
+    /// Add to the context of this error, wrapping it in a [`DiprError::Context`] if needed
+    pub(crate) fn with_context(self, f: impl FnOnce(&mut ErrorContext)) -> Self {
+        let (mut context, source) = match self {
+            DiprError::Context { context, source } => (context, source),
+            e => {
+                let remaining = match e {
+                    DiprError::Truncated { available, .. } => Some(available),
+                    _ => None,
+                };
+                let context = ErrorContext {
+                    stream: Stream::Input,
+                    offset: None,
+                    section: "",
+                    field: None,
+                    radial: None,
+                    bin: None,
+                    remaining,
+                };
+                (context, Box::new(e))
+            }
+        };
+        f(&mut context);
+        DiprError::Context { context, source }
+    }
+
+    /// Fill in the section and offset of an error raised somewhere inside `section`
+    ///
+    /// The parsers only know how much input was left when they failed, so the offset is
+    /// reconstructed from `stream_len`, the total length of `stream`. Any section that was already
+    /// recorded is kept so that the innermost section wins.
+    pub(crate) fn locate(self, section: &'static str, stream: Stream, stream_len: usize) -> Self {
+        let DiprError::Context {
+            mut context,
+            source,
+        } = self.with_context(|_| {})
+        else {
+            unreachable!("with_context always returns DiprError::Context");
+        };
+        if context.section.is_empty() {
+            context.section = section;
+        }
+        context.stream = stream;
+        if let Some(remaining) = context.remaining {
+            context.offset = Some(stream_len - remaining);
+        }
+        let source = match *source {
+            DiprError::Truncated {
+                section: "",
+                needed,
+                available,
+                ..
+            } => DiprError::Truncated {
+                section: context.section,
+                offset: stream_len - available,
+                needed,
+                available,
+            },
+            e => e,
+        };
+        DiprError::Context {
+            context,
+            source: Box::new(source),
+        }
+    }
 }
 
 impl Display for DiprError {
@@ -38,18 +249,58 @@ impl Display for DiprError {
             DiprError::InvalidCaptureTime(t) => {
                 write!(f, "Failed to parse capture time: 0x{:02x}", t)
             }
+            DiprError::Io(e) => write!(f, "I/O error: {}", e),
             DiprError::DecompressionFailed(d) => {
                 write!(f, "Failed to decompress product symbology: {}", d)
             }
+            DiprError::PayloadTooLarge { limit } => write!(
+                f,
+                "Decompressed product symbology is larger than the limit of {limit} bytes"
+            ),
+            DiprError::LimitExceeded {
+                name,
+                limit,
+                actual,
+            } => write!(f, "Found {actual} for {name}, but the limit is {limit}"),
+            DiprError::UnwrapFailed { wrapper, source } => {
+                write!(f, "Failed to unwrap {wrapper} container: {source}")
+            }
+            DiprError::UncompressedSizeMismatch { expected, actual } => write!(
+                f,
+                "Decompressed product symbology is {actual} bytes, but the product description says {expected}"
+            ),
             DiprError::InvalidUtf8String(u) => write!(f, "Failed to parse UTF-8 string: {}", u),
             DiprError::InvalidByteSlice(s) => write!(f, "Failed to parse byte slice: {}", s),
             DiprError::ValueOutOfRange(s) => write!(f, "Value out of specified range: {}", s),
             DiprError::Unsupported(s) => write!(f, "{}", s),
+            DiprError::Unencodable(s) => write!(f, "Failed to encode product: {}", s),
+            DiprError::Truncated {
+                section,
+                offset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "Input truncated in {section} at byte {offset}: needed {needed} bytes, but only {available} remain"
+            ),
+            DiprError::Context { context, .. } => write!(f, "Failed to parse {context}"),
         }
     }
 }
 
-impl Error for DiprError {}
+impl Error for DiprError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DiprError::Io(e) => Some(e),
+            DiprError::DecompressionFailed(e) => Some(e),
+            DiprError::UnwrapFailed { source, .. } => Some(source),
+            DiprError::InvalidUtf8String(e) => Some(e),
+            DiprError::InvalidByteSlice(e) => Some(e),
+            DiprError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<TryFromSliceError> for DiprError {
     fn from(value: TryFromSliceError) -> Self {
@@ -65,6 +316,89 @@ impl From<FromUtf8Error> for DiprError {
 
 impl From<io::Error> for DiprError {
     fn from(value: io::Error) -> Self {
-        DiprError::DecompressionFailed(value)
+        DiprError::Io(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SynthConfig, parse_dipr, utils::take_i16};
+
+    /// Absolute offset of the operational mode, which is halfword 17
+    const OPERATIONAL_MODE_OFFSET: usize = 62;
+
+    #[test]
+    fn locate_turns_remaining_bytes_into_offsets() {
+        let e = take_i16(&[1])
+            .unwrap_err()
+            .locate("text header", Stream::Input, 10);
+        let context = e.context().unwrap();
+        assert_eq!(context.section, "text header");
+        assert_eq!(context.offset, Some(9));
+        assert!(matches!(
+            e.without_context(),
+            DiprError::Truncated {
+                section: "text header",
+                offset: 9,
+                needed: 2,
+                available: 1,
+            }
+        ));
+    }
+
+    #[test]
+    fn innermost_section_wins() {
+        let e = DiprError::ValueOutOfRange("width".to_string())
+            .with_context(|c| {
+                c.section = "radial";
+                c.radial = Some(3);
+                c.remaining = Some(40);
+            })
+            .locate("product symbology", Stream::Decompressed, 100);
+        let context = e.context().unwrap();
+        assert_eq!(context.section, "radial");
+        assert_eq!(context.stream, Stream::Decompressed);
+        assert_eq!(context.radial, Some(3));
+        assert_eq!(context.offset, Some(60));
+        assert_eq!(
+            context.to_string(),
+            "radial, radial 3 at byte 60 of the decompressed product symbology stream"
+        );
+    }
+
+    #[test]
+    fn sources_lead_to_the_underlying_error() {
+        let e = DiprError::from(io::Error::other("disk on fire"))
+            .with_context(|c| c.section = "text header");
+        let source = e.source().unwrap().downcast_ref::<DiprError>().unwrap();
+        assert!(matches!(source, DiprError::Io(_)));
+        assert_eq!(source.source().unwrap().to_string(), "disk on fire");
+        assert!(matches!(e.without_context(), DiprError::Io(_)));
+        assert!(source.context().is_none());
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_offending_field() {
+        let mut product = SynthConfig {
+            num_bins: 2,
+            ..Default::default()
+        }
+        .encode()
+        .unwrap();
+        product[OPERATIONAL_MODE_OFFSET..OPERATIONAL_MODE_OFFSET + 2]
+            .copy_from_slice(&7i16.to_be_bytes());
+        let e = parse_dipr(&product).unwrap_err();
+        let context = e.context().unwrap();
+        assert_eq!(context.section, "product description block");
+        assert_eq!(context.field, Some("operational mode"));
+        assert_eq!(context.stream, Stream::Input);
+        assert_eq!(context.offset, Some(OPERATIONAL_MODE_OFFSET));
+        assert!(matches!(e.without_context(), DiprError::ValueOutOfRange(_)));
+        assert_eq!(
+            e.to_string(),
+            "Failed to parse product description block, field operational mode at byte 62 of the \
+             input stream"
+        );
     }
 }
@@ -0,0 +1,739 @@
+use std::{
+    f64::consts::{PI, TAU},
+    fmt::Display,
+    sync::LazyLock,
+};
+
+use geo::{Coord, LineString, MultiPolygon, Point as GeoPoint, Polygon as GeoPolygon};
+use geographiclib_rs::{DirectGeodesic, Geodesic};
+
+/// Model of the Earth's shape that bin vertices are computed on
+///
+/// DIPR products don't specify a coordinate reference system (CRS), so this is a choice between
+/// speed and accuracy. The exporters record it alongside the bins.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Datum {
+    /// Sphere with the Earth's mean radius
+    ///
+    /// This is fast, and it's likely [good enough for most purposes][xkcd 2170], but vertices at
+    /// the far edge of the coverage area can be off by hundreds of meters.
+    ///
+    /// [xkcd 2170]: https://xkcd.com/2170/
+    #[default]
+    Sphere,
+    /// WGS84 ellipsoid, with each vertex found by solving the direct geodesic problem
+    ///
+    /// The geodesics are accurate to a few nanometers, but this is several times slower.
+    Wgs84,
+}
+
+impl Datum {
+    /// Radius of [`Datum::Sphere`], which is the mean radius of the WGS84 ellipsoid
+    pub const SPHERE_RADIUS_METERS: f64 = 6371008.8;
+
+    /// Short name of this datum, e.g., `WGS84`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Datum::Sphere => "sphere",
+            Datum::Wgs84 => "WGS84",
+        }
+    }
+
+    /// Geographic coordinate system of this datum in the Esri well-known text format used by
+    /// Shapefile `.prj` files
+    pub fn wkt(&self) -> &'static str {
+        match self {
+            Datum::Sphere => {
+                r#"GEOGCS["GCS_Sphere_Mean_Radius",DATUM["D_Sphere_Mean_Radius",SPHEROID["Sphere_Mean_Radius",6371008.8,0.0]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#
+            }
+            Datum::Wgs84 => {
+                r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#
+            }
+        }
+    }
+}
+
+impl Display for Datum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How closely the inner and outer arcs of each bin are followed
+///
+/// Bins are bounded by circular arcs around the radar station, which polygons can only
+/// approximate with chords. Every bin in a radial gets the same number of chords so that
+/// consecutive bins share their vertices.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ArcDensity {
+    /// Split each arc into this many chords, which is clamped to
+    /// 1..=[`ArcDensity::MAX_SEGMENTS`]
+    Segments(u16),
+    /// Use as few chords as keep each of them within this many meters of the arc it stands for
+    /// at the far end of the radial, up to [`ArcDensity::MAX_SEGMENTS`]
+    MaxDeviation(f64),
+}
+
+impl ArcDensity {
+    /// Largest number of chords that an arc is split into
+    pub const MAX_SEGMENTS: u16 = 64;
+
+    /// Number of chords for the arcs of a radial that's `width_rad` wide and reaches out to
+    /// `max_range_meters`
+    pub(crate) fn segments(&self, width_rad: f64, max_range_meters: f64) -> usize {
+        let segments = match *self {
+            ArcDensity::Segments(segments) => segments as f64,
+            ArcDensity::MaxDeviation(meters) => {
+                // a chord spanning an angle of 2θ lies at most r(1 - cos θ) from its arc
+                let max_half_angle = (1. - meters / max_range_meters).clamp(-1., 1.).acos();
+                (width_rad / (2. * max_half_angle)).ceil()
+            }
+        };
+        // max() also turns NaN, e.g., from a zero tolerance at zero range, into a single chord
+        segments.max(1.).min(Self::MAX_SEGMENTS as f64) as usize
+    }
+}
+
+/// Two chords per arc, which meet at the center of the bin
+impl Default for ArcDensity {
+    fn default() -> Self {
+        ArcDensity::Segments(2)
+    }
+}
+
+/// Settings that control which bins are exported and how their polygons are built
+///
+/// The [`Default`] implementation gives the same behavior as
+/// [`PrecipRate::into_bins_iter`](crate::PrecipRate::into_bins_iter).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeometryOptions {
+    /// Model of the Earth's shape that bin vertices are computed on
+    pub datum: Datum,
+    /// How closely the inner and outer arcs of each bin are followed
+    pub arc_density: ArcDensity,
+    /// Whether to split bins that cross the antimeridian into one polygon on each side of it, as
+    /// [RFC 7946] requires of GeoJSON
+    ///
+    /// Otherwise, the vertices of such bins are kept next to each other, so some of their
+    /// longitudes are a little past ±180°.
+    ///
+    /// [RFC 7946]: https://www.rfc-editor.org/rfc/rfc7946#section-3.1.9
+    pub split_antimeridian: bool,
+    /// Whether to skip bins that don't hold a measured value (see [`BinValue`](crate::BinValue)),
+    /// so that a zero rate or accumulation always means that no precipitation was detected
+    pub skip_missing: bool,
+}
+
+/// Largest gap in radians between the edges of two radials that are taken to be the same edge
+///
+/// Azimuths and widths are stored in single precision, so the right edge of one radial and the left
+/// edge of the next one can be a few ulps apart. This is about 2 m at 230 km, which is far larger
+/// than that and far smaller than a radial.
+const EDGE_TOLERANCE_RAD: f64 = 1e-5;
+
+/// Move `azimuth` onto `edge`, or onto `edge` plus or minus whole turns, if they're within
+/// [`EDGE_TOLERANCE_RAD`] of each other
+pub(crate) fn snap_to_edge(azimuth: f64, edge: f64) -> Option<f64> {
+    let turns = ((azimuth - edge) / TAU).round();
+    let snapped = edge + turns * TAU;
+    ((azimuth - snapped).abs() <= EDGE_TOLERANCE_RAD).then_some(snapped)
+}
+
+/// Parameters of the WGS84 ellipsoid, which are costly to derive
+static WGS84: LazyLock<Geodesic> = LazyLock::new(Geodesic::wgs84);
+
+/// Finds the points at given bearings and distances from a radar station
+///
+/// Points are computed in double precision by the same deterministic steps every time, so a given
+/// bearing and distance always yield a bit-identical point, whichever bin asks for it.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Locator {
+    Sphere {
+        origin_rad: GeoPoint<f64>,
+        origin_lat_sin: f64,
+        origin_lat_cos: f64,
+    },
+    Wgs84 {
+        geodesic: &'static Geodesic,
+        origin: GeoPoint<f64>,
+    },
+}
+
+impl Locator {
+    pub(crate) fn new(origin: GeoPoint<f64>, datum: Datum) -> Self {
+        match datum {
+            Datum::Sphere => {
+                let origin_rad = origin.to_radians();
+                Locator::Sphere {
+                    origin_rad,
+                    origin_lat_sin: origin_rad.y().sin(),
+                    origin_lat_cos: origin_rad.y().cos(),
+                }
+            }
+            Datum::Wgs84 => Locator::Wgs84 {
+                geodesic: &WGS84,
+                origin,
+            },
+        }
+    }
+
+    /// Point at `meters` from the radar station along `bearing_rad`
+    pub(crate) fn destination(&self, bearing_rad: f64, meters: f64) -> GeoPoint<f64> {
+        match *self {
+            Locator::Sphere {
+                origin_rad,
+                origin_lat_sin,
+                origin_lat_cos,
+            } => destination(
+                origin_rad,
+                origin_lat_sin,
+                origin_lat_cos,
+                bearing_rad,
+                meters,
+            ),
+            Locator::Wgs84 { geodesic, origin } => {
+                let (lat, lng) =
+                    geodesic.direct(origin.y(), origin.x(), bearing_rad.to_degrees(), meters);
+                GeoPoint::new(lng, lat)
+            }
+        }
+    }
+}
+
+/// Point at `meters` from the origin along `bearing_rad` on [`Datum::Sphere`]
+///
+/// The origin is given in radians along with the sine and cosine of its latitude, which are the
+/// same for every vertex of a product.
+fn destination(
+    origin_rad: GeoPoint<f64>,
+    origin_lat_sin: f64,
+    origin_lat_cos: f64,
+    bearing_rad: f64,
+    meters: f64,
+) -> GeoPoint<f64> {
+    let origin_lng = origin_rad.x();
+
+    let rad = meters / Datum::SPHERE_RADIUS_METERS;
+
+    let lat =
+        { origin_lat_sin * rad.cos() + origin_lat_cos * rad.sin() * bearing_rad.cos() }.asin();
+    let y = bearing_rad.sin() * rad.sin() * origin_lat_cos;
+    let x = rad.cos() - origin_lat_sin * lat.sin();
+    let lng = y.atan2(x) + origin_lng;
+
+    // wrap around the antimeridian into -180°..180°
+    let lng = (lng + PI).rem_euclid(TAU) - PI;
+
+    GeoPoint::new(lng.to_degrees(), lat.to_degrees())
+}
+
+/// Vertices of the bins of a product, each computed at most once
+///
+/// Vertices sit at the crossings of columns, which run along the azimuths that a radial's arcs are
+/// split at, and rows, which are the boundaries between consecutive bins. Consecutive bins share
+/// a row, and neighboring radials share the column at the edge between them, so building the
+/// bins one at a time would find most vertices several times over. Vertices are only computed
+/// when a bin needs them, since exports that skip empty bins may need few of them.
+///
+/// Radials are visited one at a time with [`VertexGrid::start_radial`], and the columns at the
+/// edges of the last one are kept for the next, which shares one of them if they're adjacent. The
+/// left edge of the first radial is kept too, so the last radial of a full sweep can share it.
+#[derive(Clone, Debug)]
+pub(crate) struct VertexGrid {
+    locator: Locator,
+    /// Distance of each row from the radar station in meters
+    ranges: Vec<f64>,
+    /// The radar station, which stands in for every vertex in a row at or before it
+    origin: GeoPoint<f64>,
+    /// Columns of the current radial from its left edge to its right edge
+    columns: Vec<VertexColumn>,
+    /// Column at the left edge of the first radial, which the last one shares in a full sweep
+    sweep_start: Option<VertexColumn>,
+    /// Number of radials started so far
+    num_radials: usize,
+}
+
+/// Vertices along one azimuth, with [`None`] for those that haven't been needed yet
+#[derive(Clone, Debug)]
+struct VertexColumn {
+    azimuth: f64,
+    vertices: Vec<Option<GeoPoint<f64>>>,
+}
+
+impl VertexGrid {
+    /// Grid for radials of up to `num_bins` bins, the first of which is centered at
+    /// `range_to_first_bin` meters from the radar station
+    pub(crate) fn new(
+        locator: Locator,
+        range_to_first_bin: f64,
+        bin_size: f64,
+        num_bins: usize,
+    ) -> Self {
+        VertexGrid {
+            locator,
+            ranges: (0..=num_bins)
+                .map(|row| range_to_first_bin + bin_size * (row as f64 - 0.5))
+                .collect(),
+            origin: locator.destination(0., 0.),
+            columns: Vec::new(),
+            sweep_start: None,
+            num_radials: 0,
+        }
+    }
+
+    /// Move on to a radial whose arcs are split at `azimuths`, in radians from its left edge to
+    /// its right edge
+    pub(crate) fn start_radial(&mut self, azimuths: &[f64]) {
+        let mut previous = std::mem::take(&mut self.columns);
+        let last = previous.pop();
+        let first = previous.into_iter().next();
+        let mut edges = [first, last, self.sweep_start.take()];
+        self.columns = azimuths
+            .iter()
+            .map(|azimuth| {
+                // an edge that's reused keeps its own azimuth, which may be a whole turn away
+                edges
+                    .iter_mut()
+                    .find(|edge| {
+                        edge.as_ref()
+                            .is_some_and(|edge| snap_to_edge(*azimuth, edge.azimuth).is_some())
+                    })
+                    .and_then(Option::take)
+                    .unwrap_or_else(|| VertexColumn {
+                        azimuth: *azimuth,
+                        vertices: vec![None; self.ranges.len()],
+                    })
+            })
+            .collect();
+        let [first, _, sweep_start] = edges;
+        self.sweep_start = if self.num_radials == 1 {
+            first
+        } else {
+            sweep_start
+        };
+        self.num_radials += 1;
+    }
+
+    /// Polygon of the bin at `bin_idx` in the current radial
+    pub(crate) fn bin(&mut self, bin_idx: usize) -> GeoPolygon<f64> {
+        // a bin that starts at the radar station is a circle sector with one inner vertex
+        let inner = if self.ranges[bin_idx] > 0. {
+            (0..self.columns.len())
+                .map(|column| self.vertex(column, bin_idx))
+                .collect()
+        } else {
+            vec![self.origin]
+        };
+        let outer = (0..self.columns.len())
+            .rev()
+            .map(|column| self.vertex(column, bin_idx + 1))
+            .collect::<Vec<GeoPoint<f64>>>();
+        GeoPolygon::new(inner.into_iter().chain(outer).collect(), vec![])
+    }
+
+    fn vertex(&mut self, column: usize, row: usize) -> GeoPoint<f64> {
+        let VertexColumn { azimuth, vertices } = &mut self.columns[column];
+        *vertices[row].get_or_insert_with(|| self.locator.destination(*azimuth, self.ranges[row]))
+    }
+}
+
+/// Keep the vertices of a bin next to each other when it crosses the antimeridian
+///
+/// [`Locator::destination`] gives longitudes in -180°..=180°, so a bin that crosses the
+/// antimeridian would otherwise stretch the other way around the world. Every longitude is moved
+/// by a whole turn to within 180° of the first vertex, which leaves some a little past ±180°.
+pub(crate) fn unwrap_antimeridian(polygon: GeoPolygon<f64>) -> GeoPolygon<f64> {
+    let (exterior, interiors) = polygon.into_inner();
+    let Some(first) = exterior.0.first().copied() else {
+        return GeoPolygon::new(exterior, interiors);
+    };
+    let unwrap = |ring: LineString<f64>| {
+        ring.into_iter()
+            .map(|c| Coord {
+                x: if c.x - first.x > 180. {
+                    c.x - 360.
+                } else if c.x - first.x < -180. {
+                    c.x + 360.
+                } else {
+                    c.x
+                },
+                y: c.y,
+            })
+            .collect::<LineString<f64>>()
+    };
+    GeoPolygon::new(
+        unwrap(exterior),
+        interiors.into_iter().map(unwrap).collect(),
+    )
+}
+
+/// Split a bin from [`unwrap_antimeridian`] into one polygon on each side of the antimeridian
+///
+/// Bins that don't cross it are returned as they are. The antimeridian is crossed along a straight
+/// line in longitude and latitude, which is how GeoJSON interprets edges anyway.
+pub(crate) fn split_antimeridian(polygon: GeoPolygon<f64>) -> MultiPolygon<f64> {
+    let exterior = polygon.exterior();
+    let (min_x, max_x) = exterior
+        .coords()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), c| {
+            (min.min(c.x), max.max(c.x))
+        });
+    // bins are much smaller than a hemisphere, so they can only cross one side
+    let shift = if max_x > 180. {
+        0.
+    } else if min_x < -180. {
+        360.
+    } else {
+        return MultiPolygon::new(vec![polygon]);
+    };
+    let coords = exterior
+        .coords()
+        .map(|c| Coord {
+            x: c.x + shift,
+            y: c.y,
+        })
+        .collect::<Vec<Coord<f64>>>();
+
+    let west = clip_at_antimeridian(&coords, |x| x <= 180., 0.);
+    let east = clip_at_antimeridian(&coords, |x| x >= 180., -360.);
+    MultiPolygon::new(
+        [west, east]
+            .into_iter()
+            .filter(|ring| ring.len() >= 3)
+            .map(|ring| GeoPolygon::new(LineString::new(ring), vec![]))
+            .collect(),
+    )
+}
+
+/// Clip a closed ring to the side of the 180° meridian where `inside` holds, moving the result by
+/// `shift` degrees of longitude
+///
+/// This is one step of the Sutherland-Hodgman algorithm, which is exact for convex rings like the
+/// bins.
+fn clip_at_antimeridian(
+    ring: &[Coord<f64>],
+    inside: impl Fn(f64) -> bool,
+    shift: f64,
+) -> Vec<Coord<f64>> {
+    let mut clipped = Vec::with_capacity(ring.len() + 2);
+    for edge in ring.windows(2) {
+        let (start, end) = (edge[0], edge[1]);
+        if inside(start.x) {
+            clipped.push(Coord {
+                x: start.x + shift,
+                y: start.y,
+            });
+        }
+        if (start.x - 180.) * (end.x - 180.) < 0. {
+            let t = (180. - start.x) / (end.x - start.x);
+            clipped.push(Coord {
+                x: 180. + shift,
+                y: start.y + t * (end.y - start.y),
+            });
+        }
+    }
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use geo::{Bearing, CoordsIter, Distance, HaversineMeasure, polygon};
+    use uom::si::{angle::radian, length::meter};
+
+    use super::*;
+    use crate::{SynthConfig, parse_dipr};
+
+    /// Tolerance in degrees, which is well under a millimeter
+    const EPSILON: f64 = 1e-9;
+
+    fn assert_destination(
+        datum: Datum,
+        (lat, lng): (f64, f64),
+        bearing: f64,
+        meters: f64,
+        (expected_lat, expected_lng): (f64, f64),
+        epsilon: f64,
+    ) {
+        let point =
+            Locator::new(GeoPoint::new(lng, lat), datum).destination(bearing.to_radians(), meters);
+        assert!(
+            (point.y() - expected_lat).abs() < epsilon
+                && (point.x() - expected_lng).abs() < epsilon,
+            "{bearing}° and {meters} m from ({lat}, {lng}) on the {datum}: expected \
+             ({expected_lat}, {expected_lng}), got ({}, {})",
+            point.y(),
+            point.x()
+        );
+    }
+
+    /// Check the point at `meters` along `bearing` from `(lat, lng)` on the sphere by solving the
+    /// inverse problem with the haversine formula, which shares no code with [`destination`]
+    fn assert_sphere_round_trip(
+        (lat, lng): (f64, f64),
+        bearing: f64,
+        meters: f64,
+    ) -> GeoPoint<f64> {
+        let origin = GeoPoint::new(lng, lat);
+        let point = Locator::new(origin, Datum::Sphere).destination(bearing.to_radians(), meters);
+        let sphere = HaversineMeasure::new(Datum::SPHERE_RADIUS_METERS);
+        let distance = sphere.distance(origin, point);
+        let back_bearing = sphere.bearing(origin, point);
+        assert!(
+            (distance - meters).abs() < 1e-3 && (back_bearing - bearing).abs() < 1e-7,
+            "{bearing}° and {meters} m from ({lat}, {lng}) on the sphere: got {point:?}, which is \
+             {distance} m away along {back_bearing}°"
+        );
+        assert!((-180. ..=180.).contains(&point.x()), "got {point:?}");
+        point
+    }
+
+    #[test]
+    fn sphere_quarter_turn_east_along_equator() {
+        let quarter = std::f64::consts::FRAC_PI_2 * Datum::SPHERE_RADIUS_METERS;
+        assert_destination(Datum::Sphere, (0., 0.), 90., quarter, (0., 90.), EPSILON);
+    }
+
+    #[test]
+    fn sphere_due_north_along_meridian() {
+        // the arc length along a meridian is the radius times the latitude in radians
+        let expected = ((1e6 / Datum::SPHERE_RADIUS_METERS).to_degrees(), 0.);
+        assert_destination(Datum::Sphere, (0., 0.), 0., 1e6, expected, EPSILON);
+    }
+
+    #[test]
+    fn sphere_without_wrapping() {
+        // Andersen AFB, Guam
+        assert_sphere_round_trip((13.455, 144.811), 45., 230e3);
+        // Nome, Alaska
+        assert_sphere_round_trip((64.5, -165.3), 315., 460e3);
+    }
+
+    #[test]
+    fn sphere_wraps_east_across_antimeridian() {
+        // Aleutian Islands
+        let point = assert_sphere_round_trip((51.88, 179.5), 90., 100e3);
+        assert!(point.x() < -179., "got {point:?}");
+    }
+
+    #[test]
+    fn sphere_wraps_west_across_antimeridian() {
+        // American Samoa
+        let point = assert_sphere_round_trip((-14.33, -170.7), 270., 1.2e6);
+        assert!(point.x() > 178., "got {point:?}");
+    }
+
+    #[test]
+    fn sphere_long_distance() {
+        assert_sphere_round_trip((40.6, -73.8), 45., 1e7);
+    }
+
+    #[test]
+    fn wgs84_long_distance() {
+        // example from the GeodSolve documentation, which is given to 5 decimal places
+        let expected = (49.014_67, 2.561_06);
+        let origin = (40.639_722_22, -73.778_888_89);
+        assert_destination(Datum::Wgs84, origin, 53.5, 5.85e6, expected, 0.5e-5);
+        // example from the geographiclib-rs documentation
+        let expected = (32.621_100_463_725_796, 49.052_487_092_959_836);
+        assert_destination(Datum::Wgs84, (40.64, -73.78), 45., 1e7, expected, EPSILON);
+    }
+
+    #[test]
+    fn wgs84_wraps_across_antimeridian() {
+        for ((lat, lng), bearing) in [((51.88, 179.5), 90.), ((-14.33, -170.7), 270.)] {
+            let point = Locator::new(GeoPoint::new(lng, lat), Datum::Wgs84)
+                .destination(f64::to_radians(bearing), 1.2e6);
+            assert!((-180. ..=180.).contains(&point.x()), "got {point:?}");
+            assert!((point.x() - lng).abs() > 180., "got {point:?}");
+        }
+    }
+
+    #[test]
+    fn arc_segments_are_clamped() {
+        let width = 1f64.to_radians();
+        let max = ArcDensity::MAX_SEGMENTS as usize;
+        assert_eq!(ArcDensity::default().segments(width, 230e3), 2);
+        assert_eq!(ArcDensity::Segments(0).segments(width, 230e3), 1);
+        assert_eq!(ArcDensity::Segments(u16::MAX).segments(width, 230e3), max);
+        assert_eq!(ArcDensity::MaxDeviation(0.).segments(width, 0.), 1);
+        assert_eq!(ArcDensity::MaxDeviation(0.).segments(width, 230e3), max);
+    }
+
+    #[test]
+    fn arc_segments_meet_max_deviation() {
+        // a single 1° chord at 230 km strays r(1 - cos 0.5°) ≈ 8.76 m from the arc
+        let width = 1f64.to_radians();
+        assert_eq!(ArcDensity::MaxDeviation(10.).segments(width, 230e3), 1);
+        assert_eq!(ArcDensity::MaxDeviation(8.).segments(width, 230e3), 2);
+        // halving the chord divides the deviation by about four
+        assert_eq!(ArcDensity::MaxDeviation(2.).segments(width, 230e3), 3);
+    }
+
+    #[test]
+    fn grid_matches_locator() {
+        let locator = Locator::new(GeoPoint::new(-96., 41.), Datum::Wgs84);
+        let mut grid = VertexGrid::new(locator, 250., 500., 4);
+        let radials = [[0.1, 0.15, 0.2], [0.2, 0.25, 0.3]];
+        for azimuths in radials {
+            grid.start_radial(&azimuths);
+            for bin_idx in 0..4 {
+                let (inner, outer) = (bin_idx as f64 * 500., (bin_idx + 1) as f64 * 500.);
+                let expected = azimuths
+                    .iter()
+                    .map(|azimuth| locator.destination(*azimuth, inner))
+                    .chain(
+                        azimuths
+                            .iter()
+                            .rev()
+                            .map(|a| locator.destination(*a, outer)),
+                    );
+                let expected = if bin_idx == 0 {
+                    std::iter::once(locator.destination(0., 0.))
+                        .chain(expected.skip(azimuths.len()))
+                        .collect::<Vec<GeoPoint<f64>>>()
+                } else {
+                    expected.collect()
+                };
+                assert_eq!(
+                    grid.bin(bin_idx),
+                    GeoPolygon::new(expected.into_iter().collect(), vec![])
+                );
+            }
+        }
+    }
+
+    /// Number of vertices that each column in `grid` has computed, keyed by its azimuth
+    fn computed_vertices(grid: &VertexGrid) -> HashMap<u64, usize> {
+        grid.columns
+            .iter()
+            .chain(&grid.sweep_start)
+            .map(|column| {
+                let computed = column.vertices.iter().flatten().count();
+                (column.azimuth.to_bits(), computed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grid_finds_each_vertex_once() {
+        let config = SynthConfig {
+            num_bins: 20,
+            ..Default::default()
+        };
+        let dipr = parse_dipr(&config.encode().unwrap()).unwrap();
+        let mut grid = VertexGrid::new(
+            Locator::new(dipr.location, Datum::Sphere),
+            dipr.range_to_first_bin.get::<meter>() as f64,
+            dipr.bin_size.get::<meter>() as f64,
+            config.num_bins,
+        );
+
+        // the radar station is always computed, and the first bin of each radial starts at it
+        let mut computed = 1;
+        let mut vertices = HashSet::new();
+        let mut previous_right = None;
+        let mut first_left = None;
+        for radial in &dipr.radials {
+            // snap the edges like the bins do, so a vertex found twice comes out bit-identical
+            let center = radial.azimuth.get::<radian>() as f64;
+            let half_width = radial.width.get::<radian>() as f64 / 2.;
+            let left = center - half_width;
+            let left = previous_right
+                .and_then(|edge| snap_to_edge(left, edge))
+                .unwrap_or(left);
+            let first_left = *first_left.get_or_insert(left);
+            let right = center + half_width;
+            let right = snap_to_edge(right, first_left).unwrap_or(right);
+            previous_right = Some(right);
+
+            let before = computed_vertices(&grid);
+            grid.start_radial(&[left, (left + right) / 2., right]);
+            // a reused column keeps its vertices and a new one has none yet, so whatever is missing
+            // was dropped along with its column
+            let after = computed_vertices(&grid);
+            computed += before
+                .iter()
+                .map(|(azimuth, count)| count - after.get(azimuth).unwrap_or(&0))
+                .sum::<usize>();
+            for bin_idx in 0..radial.values.len() {
+                let bin = grid.bin(bin_idx);
+                vertices.extend(
+                    bin.exterior()
+                        .coords()
+                        .map(|c| (c.x.to_bits(), c.y.to_bits())),
+                );
+            }
+        }
+        computed += computed_vertices(&grid).values().sum::<usize>();
+        assert_eq!(computed, vertices.len());
+    }
+
+    #[test]
+    fn unwrap_keeps_vertices_together() {
+        let bin = polygon![
+            (x: 179.9, y: 50.),
+            (x: -179.9, y: 50.),
+            (x: -179.9, y: 51.),
+            (x: 179.9, y: 51.),
+        ];
+        let unwrapped = unwrap_antimeridian(bin);
+        let xs = unwrapped
+            .exterior()
+            .coords()
+            .map(|c| c.x)
+            .collect::<Vec<f64>>();
+        assert_eq!(xs, vec![179.9, 360. - 179.9, 360. - 179.9, 179.9, 179.9]);
+    }
+
+    #[test]
+    fn split_leaves_other_bins_alone() {
+        let bin = polygon![
+            (x: 170., y: 50.),
+            (x: 171., y: 50.),
+            (x: 171., y: 51.),
+            (x: 170., y: 51.),
+        ];
+        assert_eq!(
+            split_antimeridian(bin.clone()),
+            MultiPolygon::new(vec![bin])
+        );
+    }
+
+    #[test]
+    fn split_at_antimeridian() {
+        for bin in [
+            polygon![
+                (x: 179.5, y: 50.),
+                (x: 180.5, y: 50.),
+                (x: 180.5, y: 51.),
+                (x: 179.5, y: 51.),
+            ],
+            polygon![
+                (x: -180.5, y: 50.),
+                (x: -179.5, y: 50.),
+                (x: -179.5, y: 51.),
+                (x: -180.5, y: 51.),
+            ],
+        ] {
+            let parts = split_antimeridian(bin).0;
+            assert_eq!(parts.len(), 2);
+            let (west, east) = (&parts[0], &parts[1]);
+            assert!(
+                west.exterior()
+                    .coords()
+                    .all(|c| (179.5..=180.).contains(&c.x))
+            );
+            assert!(
+                east.exterior()
+                    .coords()
+                    .all(|c| (-180. ..=-179.5).contains(&c.x))
+            );
+            assert_eq!(west.exterior().coords_count(), 5);
+            assert_eq!(east.exterior().coords_count(), 5);
+        }
+    }
+}
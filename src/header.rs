@@ -0,0 +1,189 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use geo::Point;
+use uom::si::f32::Velocity;
+
+use crate::{
+    DiprError, MessageHeader, OperationalMode, ParseOptions, ParseWarning, ProductDescription,
+    ProductType, TextHeader, Wrapper, format_wrappers, inch_per_hour, product_headers,
+    product_symbology::{ProductSymbology, symbology_header},
+    reader::DecompressedStream,
+    utils::Validator,
+    wrapper::{is_uncompressed, unwrap_input},
+};
+
+/// Metadata from the uncompressed headers of a DIPR product
+///
+/// Create this struct with [`parse_dipr_header`]. It's much cheaper than
+/// [`PrecipRate`](crate::PrecipRate) because the product symbology block is never decompressed,
+/// unless the capture time was requested.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiprHeader {
+    /// Radar station where this file was generated
+    pub station_code: String,
+    /// Product in this file, or [`None`] if this crate can't decode its bins
+    ///
+    /// Unlike the other parsers, [`parse_dipr_header`] accepts any product code, so this is a cheap
+    /// way to tell whether a file is worth parsing in full.
+    pub product_type: Option<ProductType>,
+    /// Longitude/latitude coordinates of the radar station in degrees
+    pub location: Point<f64>,
+    /// Condition of the radar station
+    pub operational_mode: OperationalMode,
+    /// Whether the radar station measured any precipitation anywhere in its coverage area
+    pub precip_detected: bool,
+    /// Highest precipitation rate found in this file
+    ///
+    /// This is only meaningful for [`ProductType::PrecipRate`].
+    pub max_precip_rate: Velocity,
+    /// Size of the product symbology block after decompression, in bytes
+    pub uncompressed_size: u32,
+    /// Moment when the scan in this file began, as given at the start of the product symbology
+    /// block
+    ///
+    /// This is `None` unless [`parse_dipr_header`] was asked to peek into the compressed data or
+    /// the product is a digital radial product, which gives the capture time in the product
+    /// description block instead.
+    pub capture_time: Option<DateTime<Utc>>,
+    /// WMO text header at the start of the file
+    pub text_header: TextHeader,
+    /// Message header that follows the text header
+    pub message_header: MessageHeader,
+    /// Every field of the product description block
+    pub product_description: ProductDescription,
+    /// Containers that were removed from around the product, outermost first
+    ///
+    /// [`Wrapper::UncompressedSymbology`] can only be detected if the compression method in the
+    /// product description block says so or if `input` extends a few bytes past the headers.
+    pub wrappers: Vec<Wrapper>,
+    /// Recoverable problems found while parsing
+    ///
+    /// This is always empty unless the headers were parsed with
+    /// [`ParseMode::Lenient`](crate::ParseMode::Lenient).
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// Parse only the headers that precede the product symbology block according to `options`
+///
+/// Unless `peek_capture_time` is set, only the first [`DiprHeader::LENGTH`] bytes of the product
+/// are read, so a truncated prefix of the file is enough. With `peek_capture_time`, just enough of
+/// the compressed data is decoded to read [`DiprHeader::capture_time`], which usually means the
+/// first bzip2 block. A gzip-wrapped product is always decompressed in full, though.
+pub fn parse_dipr_header(
+    input: &[u8],
+    options: &ParseOptions,
+    peek_capture_time: bool,
+) -> Result<DiprHeader, DiprError> {
+    let (input, wrappers) = unwrap_input(input, options.max_uncompressed_size)?;
+    parse_unwrapped_header(&input, wrappers, options, peek_capture_time)
+}
+
+/// Parse the headers of a bare product as in [`parse_dipr_header`], given the containers that
+/// were already removed from around it
+pub(crate) fn parse_unwrapped_header(
+    input: &[u8],
+    mut wrappers: Vec<Wrapper>,
+    options: &ParseOptions,
+    peek_capture_time: bool,
+) -> Result<DiprHeader, DiprError> {
+    let mut validator = Validator::new(options);
+    let ((text_header, message_header, product_description), tail) =
+        product_headers(input, &mut validator)?;
+
+    let compressed = !is_uncompressed(&product_description, tail);
+    if !compressed {
+        wrappers.push(Wrapper::UncompressedSymbology);
+    }
+
+    let digital_radial = product_description
+        .product_type()
+        .is_some_and(|p| p.is_digital_radial());
+    let capture_time = if digital_radial {
+        // these products have no product description data, so the capture time is only in the
+        // product description block
+        Some(product_description.volume_scan_time)
+    } else if peek_capture_time {
+        let mut stream = DecompressedStream::new(tail, options.max_uncompressed_size, compressed);
+        let (description_data, _) = stream.parse_next(
+            ProductSymbology::NAME,
+            None,
+            &mut validator,
+            symbology_header,
+        )?;
+        Some(description_data.volume_scan_start_time)
+    } else {
+        None
+    };
+
+    Ok(DiprHeader {
+        station_code: text_header.originator.clone(),
+        product_type: product_description.product_type(),
+        location: product_description.location,
+        operational_mode: product_description.operational_mode,
+        precip_detected: product_description.precip_detected,
+        max_precip_rate: product_description.max_precip_rate,
+        uncompressed_size: product_description.uncompressed_size,
+        capture_time,
+        text_header,
+        message_header,
+        product_description,
+        wrappers,
+        warnings: validator.into_warnings(),
+    })
+}
+
+impl DiprHeader {
+    /// Number of bytes that [`parse_dipr_header`] needs when it doesn't peek at the capture time
+    pub const LENGTH: usize = crate::HEADERS_LENGTH;
+}
+
+impl Display for DiprHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Station Code:        {}", self.station_code)?;
+        writeln!(f, "AWIPS ID:            {}", self.text_header.awips_id)?;
+        match self.product_type {
+            Some(product_type) => writeln!(f, "Product:             {}", product_type)?,
+            None => writeln!(
+                f,
+                "Product:             unsupported ({})",
+                self.product_description.product_code
+            )?,
+        }
+        if !self.wrappers.is_empty() {
+            writeln!(
+                f,
+                "Wrappers:            {}",
+                format_wrappers(&self.wrappers)
+            )?;
+        }
+        if let Some(capture_time) = self.capture_time {
+            writeln!(f, "Capture Time:        {}", capture_time)?;
+        }
+        writeln!(
+            f,
+            "Generation Time:     {}",
+            self.message_header.generation_time
+        )?;
+        writeln!(
+            f,
+            "Location:            {:.4}, {:.4}",
+            self.location.y(),
+            self.location.x()
+        )?;
+        writeln!(f, "Operational Mode:    {}", self.operational_mode)?;
+        writeln!(
+            f,
+            "Precip Detected:     {}",
+            if self.precip_detected { "Yes" } else { "No" }
+        )?;
+        if self.product_type == Some(ProductType::PrecipRate) {
+            writeln!(
+                f,
+                "Max Precip Rate:     {:.3} in/hr",
+                self.max_precip_rate.get::<inch_per_hour>()
+            )?;
+        }
+        write!(f, "Uncompressed Size:   {} bytes", self.uncompressed_size)
+    }
+}
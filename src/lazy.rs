@@ -0,0 +1,217 @@
+use geo::Point;
+use uom::si::f32::Length;
+
+use crate::{
+    Component, DiprError, MessageHeader, ParseMode, ParseOptions, ParseResult, ParseWarning,
+    ProductDescription, ProductDescriptionData, ProductType, RadialComponent, Stream, TextHeader,
+    Wrapper,
+    components::{component, radial_component_header},
+    decompress, generic_product_type, product_headers,
+    product_symbology::{ProductSymbology, symbology_header, take_component_pointer},
+    radials::{RadialView, radial_view},
+    utils::*,
+    wrapper::unwrap_input,
+};
+
+/// DIPR product whose radials are decoded on demand
+///
+/// Create this struct with [`parse_dipr_lazy`]. It holds the decompressed product symbology block
+/// along with the offset of each radial, and it hands out [`RadialView`]s that borrow from it. This
+/// is much cheaper than [`PrecipRate`](crate::PrecipRate) when only a few bins are needed.
+///
+/// ```no_run
+/// use dipr::{ParseOptions, parse_dipr_lazy};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let input = std::fs::read("sn.last")?;
+/// let dipr = parse_dipr_lazy(&input, &ParseOptions::default())?;
+/// if let Some(radial) = dipr.radial(90) {
+///     println!("{:?}", radial.value(100));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct LazyPrecipRate {
+    /// Product in this file, which decides what the bins hold
+    pub product_type: ProductType,
+    /// WMO text header at the start of the file
+    pub text_header: TextHeader,
+    /// Message header that follows the text header
+    pub message_header: MessageHeader,
+    /// Every field of the product description block
+    pub product_description: ProductDescription,
+    /// Metadata from the start of the product symbology block
+    pub description_data: ProductDescriptionData,
+    /// Radial component whose radials this product gives access to
+    ///
+    /// [`RadialComponent::radials`] is always empty here. Use [`LazyPrecipRate::radial`] or
+    /// [`LazyPrecipRate::radials`] instead.
+    pub radial_component: RadialComponent,
+    /// Components of the product symbology block other than `radial_component`
+    pub components: Vec<Component>,
+    /// Containers that were removed from around the product, outermost first
+    pub wrappers: Vec<Wrapper>,
+    /// Recoverable problems found while parsing
+    pub warnings: Vec<ParseWarning>,
+    options: ParseOptions,
+    payload: Vec<u8>,
+    radial_offsets: Vec<usize>,
+}
+
+impl LazyPrecipRate {
+    /// Longitude/latitude coordinates of the radar station in degrees
+    pub fn location(&self) -> Point<f64> {
+        self.product_description.location
+    }
+
+    /// Distance between the inner and outer extents of each bin measured radially
+    pub fn bin_size(&self) -> Length {
+        self.radial_component.bin_size
+    }
+
+    /// Distance between the radar station and the center of the nearest bin
+    pub fn range_to_first_bin(&self) -> Length {
+        self.radial_component.range_to_first_bin
+    }
+
+    /// Number of radials in the product
+    pub fn num_radials(&self) -> usize {
+        self.radial_offsets.len()
+    }
+
+    /// Radial at `radial_idx` in the order that it appears in the product
+    pub fn radial(&self, radial_idx: usize) -> Option<RadialView<'_>> {
+        let offset = *self.radial_offsets.get(radial_idx)?;
+        // every radial was already validated when the offsets were found, so any warnings here
+        // are repeats
+        let mut validator = Validator::new(&ParseOptions {
+            mode: ParseMode::Lenient,
+            ..self.options.clone()
+        });
+        radial_view(&self.payload[offset..], &mut validator, self.product_type)
+            .ok()
+            .map(|(r, _)| r)
+    }
+
+    /// Iterate over all radials in the order that they appear in the product
+    pub fn radials(&self) -> impl Iterator<Item = RadialView<'_>> {
+        (0..self.num_radials()).filter_map(|radial_idx| self.radial(radial_idx))
+    }
+
+    /// Decompressed product symbology block that the radials borrow from
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+/// Convert a byte slice into a [`LazyPrecipRate`] according to `options` or return an error
+///
+/// This decompresses the product symbology block and checks the header of every radial, but it
+/// doesn't decode any bins. Wrapped products are handled as in
+/// [`parse_dipr_with`](crate::parse_dipr_with), but digital radial products (see
+/// [`ProductType::is_digital_radial`]) aren't supported.
+pub fn parse_dipr_lazy(input: &[u8], options: &ParseOptions) -> Result<LazyPrecipRate, DiprError> {
+    let mut validator = Validator::new(options);
+    let (input, mut wrappers) = unwrap_input(input, options.max_uncompressed_size)?;
+
+    let ((text_header, message_header, product_description), tail) =
+        product_headers(&input, &mut validator)?;
+    let product_type = generic_product_type(&product_description)?;
+    let payload = decompress(
+        tail,
+        &message_header,
+        &product_description,
+        &mut validator,
+        &mut wrappers,
+    )?;
+
+    let (
+        SymbologyIndex {
+            description_data,
+            radial_component,
+            radial_offsets,
+            components,
+        },
+        _,
+    ) = index_symbology(&payload, &mut validator, product_type)
+        .map_err(|e| e.locate(ProductSymbology::NAME, Stream::Decompressed, payload.len()))?;
+    validator.locate_warnings(ProductSymbology::NAME, Stream::Decompressed, payload.len());
+
+    Ok(LazyPrecipRate {
+        product_type,
+        text_header,
+        message_header,
+        product_description,
+        description_data,
+        radial_component,
+        components,
+        wrappers,
+        warnings: validator.into_warnings(),
+        options: options.clone(),
+        payload,
+        radial_offsets,
+    })
+}
+
+/// Product symbology block with the radials of its first radial component located but not parsed
+struct SymbologyIndex {
+    description_data: ProductDescriptionData,
+    radial_component: RadialComponent,
+    radial_offsets: Vec<usize>,
+    components: Vec<Component>,
+}
+
+/// Parse the product symbology block of a product of type `product_type`, but only find where
+/// each radial of the first radial component starts
+fn index_symbology<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+    product_type: ProductType,
+) -> ParseResult<'a, SymbologyIndex> {
+    let ((description_data, number_of_components), mut tail) = symbology_header(input, validator)?;
+
+    let mut primary = None;
+    let mut components = vec![];
+    for _ in 0..number_of_components {
+        let (_, t) = take_component_pointer(tail)?;
+        let (component_type, after_type) = take_i32(t)?;
+        if component_type != Component::RADIAL_TYPE || primary.is_some() {
+            let (component, t) = component(t, validator, product_type)?;
+            components.push(component);
+            tail = t;
+            continue;
+        }
+
+        let ((radial_component, num_radials), mut t) =
+            radial_component_header(after_type, validator).map_err(|e| {
+                e.with_context(|c| {
+                    if c.section.is_empty() {
+                        c.section = RadialComponent::NAME;
+                    }
+                })
+            })?;
+        let mut radial_offsets = Vec::with_capacity(num_radials);
+        for radial_idx in 0..num_radials {
+            radial_offsets.push(input.len() - t.len());
+            (_, t) = validator.in_radial(radial_idx, |v| radial_view(t, v, product_type))?;
+        }
+        primary = Some((radial_component, radial_offsets));
+        tail = t;
+    }
+
+    let Some((radial_component, radial_offsets)) = primary else {
+        return Err(DiprError::Unsupported(
+            "found no radial component in product symbology".to_string(),
+        ));
+    };
+    Ok((
+        SymbologyIndex {
+            description_data,
+            radial_component,
+            radial_offsets,
+            components,
+        },
+        tail,
+    ))
+}
@@ -4,16 +4,20 @@
 //! The DIPR radar product is useful for observing and predicting precipitation on small time and
 //! distance scales (less than 10 km or 60 minutes). This forecasting niche is called nowcasting.
 //!
-//! NWS defines the DIPR format in [this specification document][spec].
+//! NWS defines the DIPR format in [this specification document][spec]. A few of the 8-bit digital
+//! radial products, like base reflectivity and the dual-pol accumulations, are supported as well.
+//! See [`ProductType`].
 //!
 //! [spec]: https://www.roc.noaa.gov/public-documents/icds/2620001T.pdf
 
-use std::{fmt::Display, io};
+use std::{
+    fmt::Display,
+    io::{self, Read, Write},
+};
 
 use chrono::{DateTime, Utc};
-use geo::{CoordsIter, Point as GeoPoint, Polygon as GeoPolygon, polygon};
+use geo::{CoordsIter, MultiPolygon, Point as GeoPoint, Polygon as GeoPolygon};
 use geojson::{Feature, JsonObject, JsonValue};
-use product_description::{OperationalMode, ProductDescription};
 use product_symbology::ProductSymbology;
 use shapefile::{
     Point as ShapefilePoint, Polygon as ShapefilePolygon, PolygonRing,
@@ -29,17 +33,53 @@ use uom::si::{
 #[macro_use]
 extern crate uom;
 
+mod archive;
+mod components;
+mod digital_radials;
 mod error;
+mod geometry;
+mod header;
+mod lazy;
+mod message_header;
+mod options;
+mod parameters;
 mod product_description;
+mod product_description_data;
 mod product_symbology;
+mod product_type;
 mod radials;
+mod reader;
+mod synth;
+mod text_header;
+mod thresholds;
 mod utils;
+mod wrapper;
 
-pub use error::DiprError;
-use product_description::product_description;
-use product_symbology::product_symbology;
-pub use radials::Radial;
-use utils::*;
+pub use archive::{ArchiveFilter, DiprArchive};
+pub use components::{AreaComponent, Component, RadialComponent, TextComponent};
+pub use error::{DiprError, ErrorContext, Stream};
+pub use geometry::{ArcDensity, Datum, GeometryOptions};
+use geometry::{Locator, VertexGrid, snap_to_edge, split_antimeridian, unwrap_antimeridian};
+pub use header::{DiprHeader, parse_dipr_header};
+pub use lazy::{LazyPrecipRate, parse_dipr_lazy};
+pub use message_header::MessageHeader;
+use message_header::{encode_message_header, message_header};
+pub use options::{ParseMode, ParseOptions, ParseWarning};
+pub use parameters::{Parameter, ParameterValue};
+pub use product_description::{OperationalMode, ProductDescription};
+use product_description::{encode_product_description, product_description};
+pub use product_description_data::ProductDescriptionData;
+use product_symbology::{digital_product_symbology, encode_product_symbology, product_symbology};
+pub use product_type::{Measurement, ProductType, Quantity};
+pub use radials::{BinValue, Radial, RadialView};
+pub use reader::DiprReader;
+pub use synth::{PrecipPattern, SynthConfig};
+pub use text_header::TextHeader;
+use text_header::{encode_text_header, text_header};
+pub use thresholds::Thresholds;
+use utils::Validator;
+pub use wrapper::Wrapper;
+use wrapper::{is_uncompressed, unwrap_input};
 
 /// Convenient wrapper around [`Result`]
 ///
@@ -53,7 +93,9 @@ pub type ParseResult<'a, T> = Result<(T, &'a [u8]), DiprError>;
 #[derive(Clone, Debug, PartialEq)]
 /// Semantically useful representation of a DIPR product file
 ///
-/// Create this struct with [parse_dipr].
+/// Create this struct with [parse_dipr]. Despite the name, this also represents the other
+/// products listed in [`ProductType`], e.g., accumulations or reflectivity, whose bins hold a
+/// different [`Quantity`].
 pub struct PrecipRate {
     /// Radar station where this file was generated
     ///
@@ -61,6 +103,15 @@ pub struct PrecipRate {
     ///
     /// [station codes]: https://www.weather.gov/media/tg/wsr88d-radar-list.pdf
     pub station_code: String,
+    /// Product in this file, which decides what the bins hold
+    pub product_type: ProductType,
+    /// WMO text header at the start of the file
+    pub text_header: TextHeader,
+    /// Message header that follows the text header
+    ///
+    /// Note that [`MessageHeader::generation_time`] is when the product was generated, which is
+    /// after [`PrecipRate::capture_time`].
+    pub message_header: MessageHeader,
     /// Moment when the scan in this file began
     pub capture_time: DateTime<Utc>,
     /// Incrementing counter to disambiguate scans
@@ -73,19 +124,53 @@ pub struct PrecipRate {
     /// This is to match the underlying convention of the `geo` crate, which ensures that the first
     /// coordinate `x` maps to the "horizontal" value (longitude) and the second coordinate `y` maps
     /// to the "vertical" value (latitude).
-    pub location: GeoPoint<f32>,
+    pub location: GeoPoint<f64>,
     /// Condition of the radar station
     pub operational_mode: OperationalMode,
     /// Whether the radar station measured any precipitation anywhere in its coverage area
     pub precip_detected: bool,
     /// Highest precipitation rate found in this file
+    ///
+    /// This is only meaningful for [`ProductType::PrecipRate`].
     pub max_precip_rate: Velocity,
     /// Distance between the inner and outer extents of each bin measured radially
     pub bin_size: Length,
     /// Distance between the radar station and the center of the nearest bin
     pub range_to_first_bin: Length,
-    /// All precipitation data contained in this DIPR file organized by azimuth
+    /// All data contained in this file organized by azimuth
     pub radials: Vec<Radial>,
+    /// Radial component that supplied [`PrecipRate::radials`], without the radials themselves
+    ///
+    /// This keeps the component's description and parameters, which are empty for digital radial
+    /// products. [`RadialComponent::radials`] is always empty here, and [`PrecipRate::bin_size`]
+    /// and [`PrecipRate::range_to_first_bin`] take precedence over the values in this struct when
+    /// encoding.
+    pub radial_component: RadialComponent,
+    /// Position of [`PrecipRate::radial_component`] in the product symbology block, i.e., how
+    /// many of [`PrecipRate::components`] precede it
+    ///
+    /// This is 0 for typical DIPR files, and [`encode_dipr`] puts the radial component back here.
+    pub radial_component_index: usize,
+    /// Components of the product symbology block other than the radial component that supplied
+    /// [`PrecipRate::radials`], in their original order
+    ///
+    /// This is empty for typical DIPR files, which contain a single radial component.
+    pub components: Vec<Component>,
+    /// Every field of the product description block, including the ones summarized above
+    pub product_description: ProductDescription,
+    /// Metadata from the start of the product symbology block
+    ///
+    /// Digital radial products don't have this, so it's filled in from the product description
+    /// block for them.
+    pub description_data: ProductDescriptionData,
+    /// Containers that were removed from around the product, outermost first
+    ///
+    /// This is empty for a typical DIPR file.
+    pub wrappers: Vec<Wrapper>,
+    /// Recoverable problems found while parsing
+    ///
+    /// This is always empty unless the file was parsed with [`ParseMode::Lenient`].
+    pub warnings: Vec<ParseWarning>,
 }
 
 unit! {
@@ -95,187 +180,202 @@ unit! {
     @inch_per_hour: 0.09144; "in/hr", "inch per hour", "inches per hour";
 }
 
-fn destination(
-    origin_rad: GeoPoint<f32>,
-    origin_lat_sin: f32,
-    origin_lat_cos: f32,
-    bearing_rad: f32,
-    meters: f32,
-) -> GeoPoint<f32> {
-    const EARTH_RADIUS_METERS: f32 = 6371008.8;
-
-    let origin_lng = origin_rad.x();
-
-    let rad = meters / EARTH_RADIUS_METERS;
-
-    let lat =
-        { origin_lat_sin * rad.cos() + origin_lat_cos * rad.sin() * bearing_rad.cos() }.asin();
-    let y = bearing_rad.sin() * rad.sin() * origin_lat_cos;
-    let x = rad.cos() - origin_lat_sin * lat.sin();
-    let y_div_x = y / x;
-    // approximate atan2 with the identity function for small values; accurate within 0.01%
-    let lng = if y_div_x < 0.017322 {
-        y_div_x
-    } else {
-        y.atan2(x)
-    } + origin_lng;
-
-    // normalize longitude
-    let lng = if lng > 180. {
-        lng - 180.
-    } else if lng < -180. {
-        lng + 180.
-    } else {
-        lng
-    };
-
-    GeoPoint::new(lng.to_degrees(), lat.to_degrees())
-}
-
 impl PrecipRate {
-    /// Iterate over all bins, giving each of their boundaries and precipitation rates in a tuple
+    /// Iterate over all bins, giving each of their boundaries and measured values in a tuple
+    ///
+    /// Every bin is included, even ones that digital radial products flag as holding no data or a
+    /// level below the threshold (see [`BinValue`]). Set [`GeometryOptions::skip_missing`] to
+    /// leave those out. Set `skip_zeros` to leave out the bins where no precipitation was found
+    /// according to [`Measurement::is_no_precipitation`].
     ///
     /// Note that while the bins are officially bounded by circle sectors, this function
-    /// approximates the bin shapes with polygons composed of line segments. Order is not guaranteed
-    /// but is likely to be identical to the input file. That is, bins within each radial are given
-    /// in increasing order of distance from the radar station, and radials are given in increasing
-    /// order of azimuth angle.
+    /// approximates the bin shapes with polygons composed of line segments, two per arc unless
+    /// [`GeometryOptions::arc_density`] says otherwise. Order is not guaranteed but is likely to be
+    /// identical to the input file. That is, bins within each radial are given in increasing order
+    /// of distance from the radar station, and radials are given in increasing order of azimuth
+    /// angle.
+    ///
+    /// Vertices are computed in double precision, and bins that share an edge get bit-identical
+    /// vertices along it as long as the edge is at the same azimuth and range in both, so topology
+    /// tools can dissolve them cleanly.
     ///
-    /// Note that DIPR product files don't seem to specify a coordinate reference system (CRS), so
-    /// this method uses a spherical Earth approximation. It's likely that this is [good enough for
-    /// most purposes][xkcd 2170].
+    /// Longitudes are within -180°..=180°, except that the vertices of a bin that crosses the
+    /// antimeridian are kept next to each other, so some are a little past ±180°.
     ///
-    /// [xkcd 2170]: https://xkcd.com/2170/
+    /// This uses the default [`GeometryOptions`], which put the vertices on a spherical Earth.
     pub fn into_bins_iter(
         self,
         skip_zeros: bool,
-    ) -> impl Iterator<Item = (GeoPolygon<f32>, Velocity)> {
+    ) -> impl Iterator<Item = (GeoPolygon<f64>, Measurement)> {
+        self.bins(skip_zeros, &GeometryOptions::default())
+    }
+    /// Iterate over all bins as in [`PrecipRate::into_bins_iter`], but build their boundaries
+    /// according to `options`
+    ///
+    /// Each bin is a single polygon unless [`GeometryOptions::split_antimeridian`] is set and the
+    /// bin crosses the antimeridian, in which case it's one polygon on each side of it.
+    pub fn into_bins_iter_with(
+        self,
+        skip_zeros: bool,
+        options: &GeometryOptions,
+    ) -> impl Iterator<Item = (MultiPolygon<f64>, Measurement)> + use<> {
+        let split = options.split_antimeridian;
+        self.bins(skip_zeros, options).map(move |(polygon, value)| {
+            if split {
+                (split_antimeridian(polygon), value)
+            } else {
+                (MultiPolygon::new(vec![polygon]), value)
+            }
+        })
+    }
+    /// Build the polygon of every bin as in [`PrecipRate::into_bins_iter`] without splitting any
+    fn bins(
+        self,
+        skip_zeros: bool,
+        options: &GeometryOptions,
+    ) -> impl Iterator<Item = (GeoPolygon<f64>, Measurement)> + use<> {
         let PrecipRate {
+            product_type,
             location,
             bin_size,
             range_to_first_bin,
             radials,
             ..
         } = self;
-        let origin = location;
+        let range_to_first_bin = range_to_first_bin.get::<meter>() as f64;
+        let bin_size = bin_size.get::<meter>() as f64;
+        let num_bins = radials.iter().map(|r| r.values.len()).max().unwrap_or(0);
+        let mut grid = VertexGrid::new(
+            Locator::new(location, options.datum),
+            range_to_first_bin,
+            bin_size,
+            num_bins,
+        );
+        let arc_density = options.arc_density;
+        let skip_missing = options.skip_missing;
+        let mut previous_right = None;
+        let mut first_left = None;
+        // radials have to visit the grid in turn, so each one's bins are built before moving on
         radials.into_iter().flat_map(move |radial| {
+            let has_value = (0..radial.values.len())
+                .map(|bin_idx| radial.has_value(bin_idx, product_type))
+                .collect::<Vec<bool>>();
             let Radial {
                 azimuth,
                 width,
-                precip_rates,
+                values,
                 ..
             } = radial;
-            let origin_rad = origin.to_radians();
-            let origin_lat_sin = origin_rad.y().sin();
-            let origin_lat_cos = origin_rad.y().cos();
-            let center_azimuth = azimuth;
-            let left_azimuth = center_azimuth - width / 2.;
-            let right_azimuth = center_azimuth + width / 2.;
-            precip_rates
+            let center_azimuth = azimuth.get::<radian>() as f64;
+            let half_width = width.get::<radian>() as f64 / 2.;
+            // the edges come out a few ulps apart from the single-precision angles, so they're
+            // snapped to the edge shared with the previous radial and, at the end of a full sweep,
+            // to the edge shared with the first one
+            let left_azimuth = center_azimuth - half_width;
+            let left_azimuth = previous_right
+                .and_then(|edge| snap_to_edge(left_azimuth, edge))
+                .unwrap_or(left_azimuth);
+            let first_left = *first_left.get_or_insert(left_azimuth);
+            let right_azimuth = center_azimuth + half_width;
+            let right_azimuth = snap_to_edge(right_azimuth, first_left).unwrap_or(right_azimuth);
+            previous_right = Some(right_azimuth);
+
+            // every bin in the radial splits its arcs at the same azimuths, so consecutive bins
+            // share their vertices, and the ends are exactly the edges shared with neighboring
+            // radials
+            let max_range = range_to_first_bin + bin_size * (values.len() as f64 - 0.5);
+            let width = right_azimuth - left_azimuth;
+            let segments = arc_density.segments(width, max_range);
+            let arc_azimuths = (0..=segments)
+                .map(|i| match i {
+                    0 => left_azimuth,
+                    i if i == segments => right_azimuth,
+                    i => left_azimuth + width * i as f64 / segments as f64,
+                })
+                .collect::<Vec<f64>>();
+            grid.start_radial(&arc_azimuths);
+
+            values
                 .into_iter()
                 .enumerate()
-                .flat_map(move |(bin_idx, precip_rate)| {
-                    if skip_zeros && precip_rate.get::<inch_per_hour>() == 0. {
-                        return None;
-                    }
-
-                    let distance_inner_meters = range_to_first_bin.get::<meter>()
-                        + bin_size.get::<meter>() * (bin_idx as f32 - 0.5);
-                    let distance_outer_meters = range_to_first_bin.get::<meter>()
-                        + bin_size.get::<meter>() * (bin_idx as f32 + 0.5);
-
-                    let center_inner = destination(
-                        origin_rad,
-                        origin_lat_sin,
-                        origin_lat_cos,
-                        center_azimuth.get::<radian>(),
-                        distance_inner_meters,
-                    );
-                    let center_outer = destination(
-                        origin_rad,
-                        origin_lat_sin,
-                        origin_lat_cos,
-                        center_azimuth.get::<radian>(),
-                        distance_outer_meters,
-                    );
-
-                    let left_inner = destination(
-                        origin_rad,
-                        origin_lat_sin,
-                        origin_lat_cos,
-                        left_azimuth.get::<radian>(),
-                        distance_inner_meters,
-                    );
-                    let left_outer = destination(
-                        origin_rad,
-                        origin_lat_sin,
-                        origin_lat_cos,
-                        left_azimuth.get::<radian>(),
-                        distance_outer_meters,
-                    );
-
-                    let right_inner = destination(
-                        origin_rad,
-                        origin_lat_sin,
-                        origin_lat_cos,
-                        right_azimuth.get::<radian>(),
-                        distance_inner_meters,
-                    );
-                    let right_outer = destination(
-                        origin_rad,
-                        origin_lat_sin,
-                        origin_lat_cos,
-                        right_azimuth.get::<radian>(),
-                        distance_outer_meters,
-                    );
-
-                    let bin_shape = if center_inner == right_inner || center_inner == left_inner {
-                        polygon!(center_inner.into(), right_outer.into(), left_outer.into(),)
-                    } else {
-                        polygon!(
-                            center_inner.into(),
-                            right_inner.into(),
-                            right_outer.into(),
-                            center_outer.into(),
-                            left_outer.into(),
-                            left_inner.into()
-                        )
-                    };
-                    Some((bin_shape, precip_rate))
+                .filter(|(bin_idx, value)| {
+                    (has_value[*bin_idx] || !skip_missing)
+                        && !(skip_zeros && value.is_no_precipitation())
                 })
+                .map(|(bin_idx, value)| (unwrap_antimeridian(grid.bin(bin_idx)), value))
+                .collect::<Vec<(GeoPolygon<f64>, Measurement)>>()
         })
     }
     /// Iterate over all precipitation bins as in [`PrecipRate::into_bins_iter`], but also convert
     /// the results into values that are useful with the [`shapefile`] crate
+    ///
+    /// Each value is given in the unit given by [`Quantity::unit`].
     pub fn into_shapefile_iter(
         self,
         skip_zeros: bool,
     ) -> impl Iterator<Item = (GenericPolygon<ShapefilePoint>, FieldValue)> {
-        self.into_bins_iter(skip_zeros)
-            .map(|(polygon, precip_rate)| {
+        self.into_shapefile_iter_with(skip_zeros, &GeometryOptions::default())
+    }
+    /// Iterate over all precipitation bins as in [`PrecipRate::into_shapefile_iter`], but build
+    /// their boundaries according to `options`
+    ///
+    /// Shapefile records don't say which datum they're on, so write [`Datum::wkt`] to a `.prj` file
+    /// alongside them.
+    pub fn into_shapefile_iter_with(
+        self,
+        skip_zeros: bool,
+        options: &GeometryOptions,
+    ) -> impl Iterator<Item = (GenericPolygon<ShapefilePoint>, FieldValue)> + use<> {
+        self.into_bins_iter_with(skip_zeros, options)
+            .map(|(polygons, value)| {
                 (
-                    ShapefilePolygon::new(PolygonRing::Outer(
-                        polygon
-                            .coords_iter()
-                            .map(|c| ShapefilePoint::new(c.x.into(), c.y.into()))
-                            .collect::<Vec<ShapefilePoint>>(),
-                    )),
-                    dbase::FieldValue::Float(Some(precip_rate.get::<inch_per_hour>())),
+                    ShapefilePolygon::with_rings(
+                        polygons
+                            .into_iter()
+                            .map(|polygon| {
+                                PolygonRing::Outer(
+                                    polygon
+                                        .coords_iter()
+                                        .map(|c| ShapefilePoint::new(c.x, c.y))
+                                        .collect::<Vec<ShapefilePoint>>(),
+                                )
+                            })
+                            .collect(),
+                    ),
+                    dbase::FieldValue::Float(Some(value.value())),
                 )
             })
     }
     /// Iterate over all precipitation bins as in [`PrecipRate::into_bins_iter`], but also convert
     /// the results into values that are useful with the [`geojson`] crate
+    ///
+    /// Each value is stored in the property named by [`Quantity::property_name`] in the unit
+    /// given by [`Quantity::unit`]. GeoJSON has no way to give a coordinate reference system, so
+    /// each feature also has a `datum` property that holds [`Datum::name`].
     pub fn into_geojson_iter(self, skip_zeros: bool) -> impl Iterator<Item = Feature> {
-        self.into_bins_iter(skip_zeros)
-            .map(|(polygon, precip_rate)| {
+        self.into_geojson_iter_with(skip_zeros, &GeometryOptions::default())
+    }
+    /// Iterate over all precipitation bins as in [`PrecipRate::into_geojson_iter`], but build
+    /// their boundaries according to `options`
+    pub fn into_geojson_iter_with(
+        self,
+        skip_zeros: bool,
+        options: &GeometryOptions,
+    ) -> impl Iterator<Item = Feature> + use<> {
+        let property_name = self.product_type.quantity().property_name();
+        let datum = options.datum;
+        self.into_bins_iter_with(skip_zeros, options)
+            .map(move |(mut polygons, value)| {
                 let mut properties = JsonObject::new();
-                properties.insert(
-                    "precipRate".to_string(),
-                    JsonValue::from(precip_rate.get::<inch_per_hour>()),
-                );
+                properties.insert(property_name.to_string(), JsonValue::from(value.value()));
+                properties.insert("datum".to_string(), JsonValue::from(datum.name()));
                 Feature {
-                    geometry: Some((&polygon).into()),
+                    // a bin is only a multipolygon when it has to be
+                    geometry: Some(if polygons.0.len() == 1 {
+                        (&polygons.0.remove(0)).into()
+                    } else {
+                        (&polygons).into()
+                    }),
                     properties: Some(properties),
                     ..Default::default()
                 }
@@ -286,7 +386,21 @@ impl PrecipRate {
 impl Display for PrecipRate {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Station Code:        {}", self.station_code)?;
+        writeln!(f, "AWIPS ID:            {}", self.text_header.awips_id)?;
+        writeln!(f, "Product:             {}", self.product_type)?;
+        if !self.wrappers.is_empty() {
+            writeln!(
+                f,
+                "Wrappers:            {}",
+                format_wrappers(&self.wrappers)
+            )?;
+        }
         writeln!(f, "Capture Time:        {}", self.capture_time)?;
+        writeln!(
+            f,
+            "Generation Time:     {}",
+            self.message_header.generation_time
+        )?;
         writeln!(f, "Operational Mode:    {}", self.operational_mode)?;
         writeln!(
             f,
@@ -294,11 +408,16 @@ impl Display for PrecipRate {
             if self.precip_detected { "Yes" } else { "No" }
         )?;
         writeln!(f, "Scan Number:         {}", self.scan_number)?;
-        writeln!(
-            f,
-            "Max Precip Rate:     {:.3} in/hr",
-            self.max_precip_rate.get::<inch_per_hour>()
-        )?;
+        if let Some(thresholds) = self.product_description.thresholds() {
+            writeln!(f, "Data Levels:         {}", thresholds)?;
+        }
+        if self.product_type == ProductType::PrecipRate {
+            writeln!(
+                f,
+                "Max Precip Rate:     {:.3} in/hr",
+                self.max_precip_rate.get::<inch_per_hour>()
+            )?;
+        }
         writeln!(
             f,
             "Bin Size:            {: >3} m",
@@ -313,63 +432,454 @@ impl Display for PrecipRate {
     }
 }
 
-fn text_header(input: &[u8]) -> ParseResult<String> {
-    let (_, tail) = take_bytes(input, 7)?;
-    let (station_code, tail) = take_bytes(tail, 4)?;
-    let (_, tail) = take_bytes(tail, 19)?;
-    match String::from_utf8(station_code.to_vec()) {
-        Ok(s) => Ok((s, tail)),
-        Err(e) => Err(e.into()),
+/// List wrappers for display, e.g., `gzip, NOAAPort`
+fn format_wrappers(wrappers: &[Wrapper]) -> String {
+    wrappers
+        .iter()
+        .map(Wrapper::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Length of the text header, message header, and product description block together
+const HEADERS_LENGTH: usize =
+    TextHeader::LENGTH + MessageHeader::LENGTH + ProductDescription::LENGTH;
+
+/// Parse the uncompressed headers that precede the product symbology block
+///
+/// `input` must start at the beginning of the product so that error offsets are correct.
+fn product_headers<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, (TextHeader, MessageHeader, ProductDescription)> {
+    let (text_header, tail) =
+        text_header(input).map_err(|e| e.locate(TextHeader::NAME, Stream::Input, input.len()))?;
+    let (message_header, tail) = message_header(tail, validator)
+        .map_err(|e| e.locate(MessageHeader::NAME, Stream::Input, input.len()))?;
+    validator.locate_warnings(MessageHeader::NAME, Stream::Input, input.len());
+    let (product_description, tail) = product_description(tail, validator)
+        .map_err(|e| e.locate(ProductDescription::NAME, Stream::Input, input.len()))?;
+    validator.locate_warnings(ProductDescription::NAME, Stream::Input, input.len());
+    Ok(((text_header, message_header, product_description), tail))
+}
+
+/// Find the product from the product code in the product description block
+fn product_type(product_description: &ProductDescription) -> Result<ProductType, DiprError> {
+    product_description.product_type().ok_or_else(|| {
+        let supported = ProductType::ALL
+            .iter()
+            .map(ProductType::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        DiprError::Unsupported(format!(
+            "found unsupported product code {}; only these products are supported: {supported}",
+            product_description.product_code
+        ))
+        .with_context(|c| {
+            c.section = ProductDescription::NAME;
+            c.field = Some("product code");
+            c.offset = Some(
+                TextHeader::LENGTH
+                    + MessageHeader::LENGTH
+                    + ProductDescription::PRODUCT_CODE_OFFSET,
+            );
+        })
+    })
+}
+
+/// Convert the volume scan number into the `u8` that [`PrecipRate::scan_number`] holds
+///
+/// In [`ParseMode::Lenient`], the range check only records an out-of-range scan number as a
+/// warning, but one that doesn't fit can't be kept, so this fails in every mode.
+fn scan_number(
+    description_data: &ProductDescriptionData,
+    product_type: ProductType,
+) -> Result<u8, DiprError> {
+    let volume_scan_number = description_data.volume_scan_number;
+    u8::try_from(volume_scan_number).map_err(|_| {
+        // digital radial products take the scan number from the product description block
+        let section = if product_type.is_digital_radial() {
+            ProductDescription::NAME
+        } else {
+            ProductDescriptionData::NAME
+        };
+        DiprError::ValueOutOfRange(format!(
+            "scan number in {section}: got {volume_scan_number}, expected {:?}",
+            0..=u8::MAX
+        ))
+        .with_context(|c| {
+            c.section = section;
+            c.field = Some("scan number");
+            if product_type.is_digital_radial() {
+                c.offset = Some(
+                    TextHeader::LENGTH
+                        + MessageHeader::LENGTH
+                        + ProductDescription::VOLUME_SCAN_NUMBER_OFFSET,
+                );
+            } else {
+                c.stream = Stream::Decompressed;
+            }
+        })
+    })
+}
+
+/// Find the product as in [`product_type`], but reject digital radial products, which only
+/// [`parse_dipr_with`] can read
+fn generic_product_type(
+    product_description: &ProductDescription,
+) -> Result<ProductType, DiprError> {
+    let product_type = product_type(product_description)?;
+    if product_type.is_digital_radial() {
+        return Err(DiprError::Unsupported(format!(
+            "{product_type} is a digital radial product, which can only be read in full with parse_dipr"
+        ))
+        .with_context(|c| {
+            c.section = ProductDescription::NAME;
+            c.field = Some("product code");
+            c.offset = Some(
+                TextHeader::LENGTH + MessageHeader::LENGTH + ProductDescription::PRODUCT_CODE_OFFSET,
+            );
+        }));
     }
+    Ok(product_type)
 }
 
-fn message_header(input: &[u8]) -> ParseResult<()> {
-    let (_, tail) = take_bytes(input, 18)?;
-    Ok(((), tail))
+/// Decompress the product symbology block, which should be all of `input` after the headers
+///
+/// A block that was stored uncompressed is copied as-is and recorded in `wrappers`. If the
+/// compressed block can't be decompressed and is shorter than the message header says it should
+/// be, the product was cut off and this fails with [`DiprError::Truncated`] rather than
+/// [`DiprError::DecompressionFailed`].
+fn decompress(
+    input: &[u8],
+    message_header: &MessageHeader,
+    product_description: &ProductDescription,
+    validator: &mut Validator,
+    wrappers: &mut Vec<Wrapper>,
+) -> Result<Vec<u8>, DiprError> {
+    let limit = validator.options().max_uncompressed_size;
+    if is_uncompressed(product_description, input) {
+        if input.len() > limit {
+            return Err(DiprError::PayloadTooLarge { limit });
+        }
+        wrappers.push(Wrapper::UncompressedSymbology);
+        return Ok(input.to_vec());
+    }
+
+    // read one byte past the limit so that we can tell whether it was exceeded
+    let uncompressed_size = product_description.uncompressed_size;
+    let mut uncompressed_payload = Vec::with_capacity((uncompressed_size as usize).min(limit));
+    let mut reader = bzip2::read::BzDecoder::new(input).take(limit as u64 + 1);
+    io::copy(&mut reader, &mut uncompressed_payload).map_err(|e| {
+        let advertised = (message_header.length as usize)
+            .saturating_sub(MessageHeader::LENGTH + ProductDescription::LENGTH);
+        if input.len() >= advertised {
+            return DiprError::DecompressionFailed(e);
+        }
+        DiprError::Truncated {
+            section: "",
+            offset: 0,
+            needed: advertised,
+            available: input.len(),
+        }
+        .with_context(|c| c.field = Some("compressed data"))
+        .locate(
+            ProductSymbology::NAME,
+            Stream::Input,
+            HEADERS_LENGTH + input.len(),
+        )
+    })?;
+    if uncompressed_payload.len() > limit {
+        return Err(DiprError::PayloadTooLarge { limit });
+    }
+    validator.check_uncompressed_size(uncompressed_size, uncompressed_payload.len())?;
+    Ok(uncompressed_payload)
 }
 
 /// Convert a byte slice into a [`PrecipRate`] or return an error
+///
+/// This is equivalent to [`parse_dipr_with`] using the default [`ParseOptions`].
 pub fn parse_dipr(input: &[u8]) -> Result<PrecipRate, DiprError> {
-    let (station_code, tail) = text_header(input)?;
-    let (_, tail) = message_header(tail)?;
+    parse_dipr_with(input, &ParseOptions::default())
+}
 
-    let (
-        ProductDescription {
-            location,
-            operational_mode,
-            precip_detected,
-            max_precip_rate,
-            uncompressed_size,
-        },
-        tail,
-    ) = product_description(tail)?;
+/// Convert a byte slice into a [`PrecipRate`] according to `options` or return an error
+///
+/// The product code decides how the bins are decoded, and products other than DIPR are accepted
+/// as long as they're listed in [`ProductType`]. Products that are wrapped in gzip or NOAAPort
+/// framing, or whose product symbology block isn't compressed, are detected and handled
+/// automatically. See [`PrecipRate::wrappers`].
+pub fn parse_dipr_with(input: &[u8], options: &ParseOptions) -> Result<PrecipRate, DiprError> {
+    let (input, wrappers) = unwrap_input(input, options.max_uncompressed_size)?;
+    parse_unwrapped(&input, wrappers, options)
+}
 
-    // decompress remaining input, which should all be compressed with bzip2
-    let mut uncompressed_payload = Vec::with_capacity(uncompressed_size as usize);
-    let mut reader = bzip2_rs::DecoderReader::new(tail);
-    io::copy(&mut reader, &mut uncompressed_payload)?;
+/// Parse a bare product as in [`parse_dipr_with`], given the containers that were already removed
+/// from around it
+pub(crate) fn parse_unwrapped(
+    input: &[u8],
+    mut wrappers: Vec<Wrapper>,
+    options: &ParseOptions,
+) -> Result<PrecipRate, DiprError> {
+    let mut validator = Validator::new(options);
+    let ((text_header, message_header, product_description), tail) =
+        product_headers(input, &mut validator)?;
+    let product_type = product_type(&product_description)?;
 
+    let uncompressed_payload = decompress(
+        tail,
+        &message_header,
+        &product_description,
+        &mut validator,
+        &mut wrappers,
+    )?;
+
+    let symbology = if product_type.is_digital_radial() {
+        digital_product_symbology(
+            &uncompressed_payload,
+            &mut validator,
+            product_type,
+            &product_description,
+            &text_header.originator,
+        )
+    } else {
+        product_symbology(&uncompressed_payload, &mut validator, product_type)
+    };
     let (
         ProductSymbology {
             range_to_first_bin,
             bin_size,
-            scan_number,
             capture_time,
             radials,
+            radial_component,
+            radial_component_index,
+            components,
+            description_data,
         },
         _,
-    ) = product_symbology(&uncompressed_payload)?;
+    ) = symbology.map_err(|e| {
+        e.locate(
+            ProductSymbology::NAME,
+            Stream::Decompressed,
+            uncompressed_payload.len(),
+        )
+    })?;
+    validator.locate_warnings(
+        ProductSymbology::NAME,
+        Stream::Decompressed,
+        uncompressed_payload.len(),
+    );
+    let scan_number = scan_number(&description_data, product_type)?;
 
     Ok(PrecipRate {
-        station_code,
+        station_code: text_header.originator.clone(),
+        product_type,
+        text_header,
+        message_header,
         capture_time,
         scan_number,
-        location,
-        operational_mode,
-        precip_detected,
-        max_precip_rate,
+        location: product_description.location,
+        operational_mode: product_description.operational_mode,
+        precip_detected: product_description.precip_detected,
+        max_precip_rate: product_description.max_precip_rate,
         bin_size,
         range_to_first_bin,
         radials,
+        radial_component,
+        radial_component_index,
+        components,
+        product_description,
+        description_data,
+        wrappers,
+        warnings: validator.into_warnings(),
     })
 }
+
+/// Convert a [`PrecipRate`] into a DIPR product in its native format or return an error
+///
+/// Only products in the generic radial format can be encoded, so this fails for digital radial
+/// products (see [`ProductType::is_digital_radial`]). It also fails if
+/// [`PrecipRate::radial_component_index`] is past the end of [`PrecipRate::components`].
+///
+/// The summarized fields of [`PrecipRate`], e.g., [`PrecipRate::product_type`] and
+/// [`PrecipRate::capture_time`], take precedence over the header structs that repeat them, so
+/// they're the ones to change when editing a product. The message length and the uncompressed
+/// size are computed from the encoded data, and the product symbology block is compressed with
+/// bzip2 at the highest level. Everything else is written as-is, except that
+/// [`PrecipRate::wrappers`] is ignored, so the output is always a bare product with compressed
+/// symbology.
+///
+/// Parsing the result with [`parse_dipr`] gives back an equal [`PrecipRate`], apart from
+/// [`PrecipRate::wrappers`], as long as every value is within the range that the format can
+/// represent. A bare product that was parsed and encoded without changes comes out byte-for-byte
+/// identical if its parameters were in order of [`Parameter::id`] with no duplicates, its
+/// fixed-width text fields weren't padded, and it was compressed the same way.
+pub fn encode_dipr(dipr: &PrecipRate) -> Result<Vec<u8>, DiprError> {
+    if dipr.product_type.is_digital_radial() {
+        return Err(DiprError::Unencodable(format!(
+            "{} is a digital radial product, which can't be encoded",
+            dipr.product_type.mnemonic()
+        )));
+    }
+    let description_data = ProductDescriptionData {
+        volume_scan_start_time: dipr.capture_time,
+        volume_scan_number: dipr.scan_number.into(),
+        product_code: dipr.product_type.code().into(),
+        ..dipr.description_data.clone()
+    };
+    let radial_component = RadialComponent {
+        description: dipr.radial_component.description.clone(),
+        bin_size: dipr.bin_size,
+        range_to_first_bin: dipr.range_to_first_bin,
+        parameters: dipr.radial_component.parameters.clone(),
+        radials: vec![],
+    };
+    let mut symbology = vec![];
+    encode_product_symbology(
+        dipr.product_type,
+        &description_data,
+        &radial_component,
+        &dipr.radials,
+        dipr.radial_component_index,
+        &dipr.components,
+        &mut symbology,
+    )?;
+
+    let mut encoder = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::best());
+    encoder.write_all(&symbology)?;
+    let compressed = encoder.finish()?;
+
+    let text_header = TextHeader {
+        originator: dipr.station_code.clone(),
+        ..dipr.text_header.clone()
+    };
+    let message_header = MessageHeader {
+        message_code: dipr.product_type.code(),
+        length: (MessageHeader::LENGTH + ProductDescription::LENGTH + compressed.len()) as u32,
+        ..dipr.message_header.clone()
+    };
+    let product_description = ProductDescription {
+        location: dipr.location,
+        product_code: dipr.product_type.code(),
+        operational_mode: dipr.operational_mode,
+        precip_detected: dipr.precip_detected,
+        max_precip_rate: dipr.max_precip_rate,
+        compression_method: ProductDescription::BZIP2_COMPRESSION,
+        uncompressed_size: symbology.len() as u32,
+        ..dipr.product_description.clone()
+    };
+
+    let mut output = Vec::with_capacity(HEADERS_LENGTH + compressed.len());
+    encode_text_header(&text_header, &mut output)?;
+    encode_message_header(&message_header, &mut output)?;
+    encode_product_description(&product_description, &mut output)?;
+    output.extend_from_slice(&compressed);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    /// Absolute offset of the uncompressed size, which is halfwords 52 and 53
+    const UNCOMPRESSED_SIZE_OFFSET: usize = 132;
+
+    pub(crate) fn product() -> Vec<u8> {
+        SynthConfig {
+            num_bins: 2,
+            ..Default::default()
+        }
+        .encode()
+        .unwrap()
+    }
+
+    /// Reader that fails once `input` runs out
+    pub(crate) struct Broken<'a>(pub(crate) &'a [u8]);
+
+    impl Read for Broken<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf)? {
+                0 => Err(io::Error::other("unplugged")),
+                read => Ok(read),
+            }
+        }
+    }
+
+    #[test]
+    fn corrupt_compressed_data_fails_decompression() {
+        let mut product = product();
+        // overwrite the magic number at the start of the first bzip2 block
+        product[HEADERS_LENGTH + 4..HEADERS_LENGTH + 10].fill(0);
+        let e = parse_dipr(&product).unwrap_err();
+        assert!(
+            matches!(e.without_context(), DiprError::DecompressionFailed(_)),
+            "{e:?}"
+        );
+        assert!(e.without_context().source().is_some());
+    }
+
+    #[test]
+    fn failed_reads_are_io_errors() {
+        let product = product();
+        let e = DiprReader::new(Broken(&product[..HEADERS_LENGTH / 2]))
+            .err()
+            .unwrap();
+        assert!(matches!(e.without_context(), DiprError::Io(_)), "{e:?}");
+        assert_eq!(e.source().unwrap().to_string(), "unplugged");
+    }
+
+    #[test]
+    fn uncompressed_size_mismatch_is_an_error_only_in_strict_mode() {
+        let mut product = product();
+        let size = parse_dipr(&product)
+            .unwrap()
+            .product_description
+            .uncompressed_size;
+        product[UNCOMPRESSED_SIZE_OFFSET..UNCOMPRESSED_SIZE_OFFSET + 4]
+            .copy_from_slice(&(size + 1).to_be_bytes());
+
+        let e = parse_dipr(&product).unwrap_err();
+        assert!(
+            matches!(
+                e.without_context(),
+                DiprError::UncompressedSizeMismatch { expected, actual }
+                    if *expected == size + 1 && *actual == size as usize
+            ),
+            "{e:?}"
+        );
+
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        let dipr = parse_dipr_with(&product, &options).unwrap();
+        assert_eq!(
+            dipr.warnings,
+            [ParseWarning::UncompressedSizeMismatch {
+                expected: size + 1,
+                actual: size as usize,
+            }]
+        );
+    }
+
+    #[test]
+    fn payload_cap_is_inclusive() {
+        let product = product();
+        let size = parse_dipr(&product)
+            .unwrap()
+            .product_description
+            .uncompressed_size as usize;
+        let options = |max_uncompressed_size| ParseOptions {
+            max_uncompressed_size,
+            ..Default::default()
+        };
+        assert!(parse_dipr_with(&product, &options(size)).is_ok());
+        let e = parse_dipr_with(&product, &options(size - 1)).unwrap_err();
+        assert!(
+            matches!(e, DiprError::PayloadTooLarge { limit } if limit == size - 1),
+            "{e:?}"
+        );
+    }
+}
@@ -1,78 +1,216 @@
 use std::{
     error::Error,
-    fs,
-    io::{Read, stdin},
+    fs::{self, File},
+    io::{Read, Write, stdin, stdout},
+    path::Path,
     sync::mpsc,
     thread::{self, JoinHandle},
 };
 
+use chrono::{DateTime, TimeDelta, Utc};
 use clap::{Parser, Subcommand};
-use dipr::{PrecipRate, parse_dipr};
+use dipr::{
+    ArcDensity, ArchiveFilter, Datum, DiprArchive, GeometryOptions, ParseMode, ParseOptions,
+    PrecipPattern, PrecipRate, ProductType, Quantity, SynthConfig, encode_dipr, inch_per_hour,
+    parse_dipr_header, parse_dipr_with,
+};
+use geo::Point as GeoPoint;
 use geojson::{FeatureCollection, GeoJson};
 use shapefile::{
     Error as ShapefileError, Point, Writer,
     dbase::{FieldValue, Record, TableWriterBuilder},
     record::polygon::GenericPolygon,
 };
+use uom::si::{
+    angle::degree,
+    f32::{Angle, Length, Velocity},
+    length::{kilometer, meter},
+    velocity::meter_per_second,
+};
 
-fn read_and_convert(input: &str) -> Result<PrecipRate, Box<dyn Error>> {
+fn read_input(input: &str) -> Result<Vec<u8>, Box<dyn Error>> {
     if input == "-" {
         let mut input_buf = vec![];
         stdin().read_to_end(&mut input_buf)?;
-        Ok(parse_dipr(&input_buf)?)
+        Ok(input_buf)
     } else {
-        let input_file = fs::read(input)?;
-        Ok(parse_dipr(&input_file)?)
+        Ok(fs::read(input)?)
+    }
+}
+
+fn write_output(output: &str, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    if output == "-" {
+        stdout().write_all(bytes)?;
+    } else {
+        fs::write(output, bytes)?;
+    }
+    Ok(())
+}
+
+fn read_and_convert(input: &str, options: &ParseOptions) -> Result<PrecipRate, Box<dyn Error>> {
+    let dipr = parse_dipr_with(&read_input(input)?, options)?;
+    for warning in &dipr.warnings {
+        eprintln!("Warning: {}", warning);
     }
+    Ok(dipr)
 }
 
 fn convert_to_shapefile(
     dipr: PrecipRate,
     skip_zeros: bool,
+    geometry: &GeometryOptions,
     output: &str,
 ) -> Result<(), Box<dyn Error>> {
     let (tx, rx) = mpsc::channel::<(GenericPolygon<Point>, FieldValue)>();
 
-    const PRECIP_RATE_FIELD_NAME: &str = "Precip Rate";
-    let table_builder =
-        TableWriterBuilder::new().add_float_field(PRECIP_RATE_FIELD_NAME.try_into().unwrap(), 5, 3);
+    let (field_name, length, decimal_count) = match dipr.product_type.quantity() {
+        Quantity::PrecipRate => ("Precip Rate", 5, 3),
+        Quantity::Accumulation => ("Accum", 6, 2),
+        Quantity::Reflectivity => ("Refl", 5, 1),
+        Quantity::Vil => ("VIL", 6, 2),
+    };
+    let table_builder = TableWriterBuilder::new().add_float_field(
+        field_name.try_into().unwrap(),
+        length,
+        decimal_count,
+    );
     let mut writer = Writer::from_path(output, table_builder)?;
     let mut record = Record::default();
 
     let writer_thread: JoinHandle<Result<(), ShapefileError>> = thread::spawn(move || {
-        for (polygon, precip_rate) in rx {
-            record.insert(PRECIP_RATE_FIELD_NAME.to_string(), precip_rate);
+        for (polygon, value) in rx {
+            record.insert(field_name.to_string(), value);
             writer.write_shape_and_record(&polygon, &record)?;
         }
         Ok(())
     });
 
-    for bin in dipr.into_shapefile_iter(skip_zeros) {
+    for bin in dipr.into_shapefile_iter_with(skip_zeros, geometry) {
         tx.send(bin)?;
     }
 
     drop(tx);
     let _ = writer_thread.join();
+    fs::write(
+        Path::new(output).with_extension("prj"),
+        geometry.datum.wkt(),
+    )?;
     Ok(())
 }
 
-fn convert_to_geojson(dipr: PrecipRate, skip_zeros: bool) -> Result<(), Box<dyn Error>> {
-    println!(
-        "{}",
-        GeoJson::FeatureCollection(FeatureCollection {
-            features: dipr.into_geojson_iter(skip_zeros).collect(),
-            ..Default::default()
-        })
-    );
+fn convert_to_geojson(
+    dipr: PrecipRate,
+    skip_zeros: bool,
+    geometry: &GeometryOptions,
+    output: &str,
+) -> Result<(), Box<dyn Error>> {
+    let geojson = GeoJson::FeatureCollection(FeatureCollection {
+        features: dipr.into_geojson_iter_with(skip_zeros, geometry).collect(),
+        ..Default::default()
+    });
+    write_output(output, format!("{geojson}\n").as_bytes())
+}
+
+fn convert_archive(
+    input: &str,
+    options: &ParseOptions,
+    filter: ArchiveFilter,
+    geojson_dir: Option<&str>,
+    skip_zeros: bool,
+    geometry: &GeometryOptions,
+) -> Result<(), Box<dyn Error>> {
+    let reader: Box<dyn Read> = if input == "-" {
+        Box::new(stdin())
+    } else {
+        Box::new(File::open(input)?)
+    };
+    let mut archive = DiprArchive::with_options(reader, options, filter)?;
+    let (mut total, mut failed) = (0, 0);
+    for (name, dipr) in archive.products()? {
+        total += 1;
+        let dipr = match dipr {
+            Ok(dipr) => dipr,
+            Err(e) => {
+                failed += 1;
+                match e.context() {
+                    Some(_) => eprintln!("Error in {name}: {e}: {}", e.without_context()),
+                    None => eprintln!("Error in {name}: {e}"),
+                }
+                continue;
+            }
+        };
+        for warning in &dipr.warnings {
+            eprintln!("Warning in {name}: {warning}");
+        }
+        println!(
+            "{name}: {} {} {}",
+            dipr.station_code,
+            dipr.product_type.mnemonic(),
+            dipr.capture_time
+        );
+        if let Some(dir) = geojson_dir {
+            let file_name = Path::new(&name).file_name().unwrap_or(name.as_ref());
+            let output = Path::new(dir).join(format!("{}.geojson", file_name.to_string_lossy()));
+            convert_to_geojson(dipr, skip_zeros, geometry, &output.to_string_lossy())?;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{failed} of {total} products failed to parse").into());
+    }
     Ok(())
 }
 
+fn parse_datum(s: &str) -> Result<Datum, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "sphere" => Ok(Datum::Sphere),
+        "wgs84" => Ok(Datum::Wgs84),
+        _ => Err(format!("invalid datum {s:?}: expected sphere or wgs84")),
+    }
+}
+
+fn parse_deviation(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(meters) if meters.is_finite() && meters > 0. => Ok(meters),
+        _ => Err(format!(
+            "invalid deviation {s:?}: expected a positive number of meters"
+        )),
+    }
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.to_utc())
+        .map_err(|e| format!("invalid time {s:?}: {e}"))
+}
+
 /// Convert the NWS Digital Instantaneous Precipitation Rate product to common vector GIS formats
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct DiprCli {
     #[command(subcommand)]
     action: Action,
+    /// Warn about recoverable problems in the input instead of failing
+    #[arg(long, global = true)]
+    lenient: bool,
+    /// Model of the Earth to compute bin vertices on when converting, either sphere (fast) or
+    /// wgs84 (accurate)
+    #[arg(long, global = true, value_parser = parse_datum, default_value = "sphere")]
+    datum: Datum,
+    /// Number of segments to approximate the inner and outer arc of each bin with when converting
+    /// [default: 2]
+    #[arg(long, global = true, conflicts_with = "max_arc_deviation")]
+    arc_segments: Option<u16>,
+    /// Approximate the inner and outer arc of each bin with as few segments as stay within this
+    /// many meters of them when converting
+    #[arg(long, global = true, value_parser = parse_deviation)]
+    max_arc_deviation: Option<f64>,
+    /// Split bins that cross the antimeridian into a polygon on each side of it when converting
+    #[arg(long, global = true)]
+    split_antimeridian: bool,
+    /// Leave out bins that are flagged as holding no data or a level below the threshold when
+    /// converting
+    #[arg(long, global = true)]
+    skip_missing: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -81,6 +219,9 @@ enum Action {
     Info {
         /// Path to the DIPR product; if equal to - (hyphen), read from stdin
         input: String,
+        /// Only read the headers and the start of the compressed data, which is much faster
+        #[arg(long)]
+        headers_only: bool,
     },
     /// Converts the input DIPR product to GeoJSON and writes it to stdout
     ToGeojson {
@@ -101,27 +242,245 @@ enum Action {
         /// /path/to/foo{.shp,.shx,.dbf}
         output: String,
     },
+    /// Parses every product in a tar archive, which may be gzip-compressed, and prints a summary of
+    /// each
+    Archive {
+        /// Path to the tar archive; if equal to - (hyphen), read from stdin
+        input: String,
+        /// Only include products from this radar station; may be repeated
+        #[arg(long)]
+        station: Vec<String>,
+        /// Only include products with this product code; may be repeated. Defaults to every
+        /// supported product
+        #[arg(long)]
+        product_code: Vec<i16>,
+        /// Only include products captured at or after this time in RFC 3339 format
+        #[arg(long, value_parser = parse_time)]
+        start: Option<DateTime<Utc>>,
+        /// Only include products captured before this time in RFC 3339 format
+        #[arg(long, value_parser = parse_time)]
+        end: Option<DateTime<Utc>>,
+        /// Also convert each product to GeoJSON and write it to this directory, named after its
+        /// entry in the archive
+        #[arg(long)]
+        geojson_dir: Option<String>,
+        /// When producing the GeoJSON output, don't include bins with zero precipitation
+        #[arg(long)]
+        skip_zeros: bool,
+    },
+    /// Writes synthetic DIPR products built from simple precipitation patterns
+    Synth {
+        /// Path to the output product; if equal to - (hyphen), write to stdout. With --count, the
+        /// products are written to this path with .0001, .0002, and so on appended
+        output: String,
+        /// Four-letter code of the radar station
+        #[arg(long, default_value = "KXXX")]
+        station: String,
+        /// Latitude of the radar station in degrees
+        #[arg(long, default_value_t = 41., allow_negative_numbers = true)]
+        latitude: f64,
+        /// Longitude of the radar station in degrees
+        #[arg(long, default_value_t = -96., allow_negative_numbers = true)]
+        longitude: f64,
+        /// Scan start time in RFC 3339 format, e.g., 2024-10-04T12:00:00Z
+        #[arg(long, value_parser = parse_time)]
+        capture_time: Option<DateTime<Utc>>,
+        /// Number of radials
+        #[arg(long, default_value_t = 360)]
+        radials: usize,
+        /// Number of bins in each radial
+        #[arg(long, default_value_t = 920)]
+        bins: usize,
+        /// Bin size in meters
+        #[arg(long, default_value_t = 250.)]
+        bin_size: f32,
+        /// Range to the center of the first bin in meters
+        #[arg(long, default_value_t = 0.)]
+        range_to_first_bin: f32,
+        /// Add concentric rings, given as SPACING_KM,PEAK_IN_HR
+        #[arg(long, value_parser = parse_rings)]
+        rings: Vec<PrecipPattern>,
+        /// Add a Gaussian storm cell, given as AZIMUTH_DEG,RANGE_KM,RADIUS_KM,PEAK_IN_HR with
+        /// optional HEADING_DEG,SPEED_M_S
+        #[arg(long, value_parser = parse_cell)]
+        cell: Vec<PrecipPattern>,
+        /// Add a moving band, given as HEADING_DEG,OFFSET_KM,WIDTH_KM,PEAK_IN_HR with optional
+        /// SPEED_M_S
+        #[arg(long, value_parser = parse_band, allow_hyphen_values = true)]
+        band: Vec<PrecipPattern>,
+        /// Number of consecutive products to write
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+        /// Minutes between consecutive products
+        #[arg(long, default_value_t = 5)]
+        interval: i64,
+    },
+}
+
+/// Split a comma-separated list of between `min` and `max` numbers
+fn parse_numbers(s: &str, min: usize, max: usize) -> Result<Vec<f32>, String> {
+    let numbers = s
+        .split(',')
+        .map(|n| n.trim().parse::<f32>().map_err(|e| format!("{n:?}: {e}")))
+        .collect::<Result<Vec<f32>, String>>()?;
+    if numbers.len() < min || numbers.len() > max {
+        return Err(format!(
+            "expected {min} to {max} comma-separated numbers, got {}",
+            numbers.len()
+        ));
+    }
+    Ok(numbers)
+}
+
+fn parse_rings(s: &str) -> Result<PrecipPattern, String> {
+    let n = parse_numbers(s, 2, 2)?;
+    Ok(PrecipPattern::Rings {
+        spacing: Length::new::<kilometer>(n[0]),
+        peak: Velocity::new::<inch_per_hour>(n[1]),
+    })
+}
+
+fn parse_cell(s: &str) -> Result<PrecipPattern, String> {
+    let n = parse_numbers(s, 4, 6)?;
+    Ok(PrecipPattern::Cell {
+        azimuth: Angle::new::<degree>(n[0]),
+        range: Length::new::<kilometer>(n[1]),
+        radius: Length::new::<kilometer>(n[2]),
+        peak: Velocity::new::<inch_per_hour>(n[3]),
+        heading: Angle::new::<degree>(n.get(4).copied().unwrap_or_default()),
+        speed: Velocity::new::<meter_per_second>(n.get(5).copied().unwrap_or_default()),
+    })
+}
+
+fn parse_band(s: &str) -> Result<PrecipPattern, String> {
+    let n = parse_numbers(s, 4, 5)?;
+    Ok(PrecipPattern::Band {
+        heading: Angle::new::<degree>(n[0]),
+        offset: Length::new::<kilometer>(n[1]),
+        width: Length::new::<kilometer>(n[2]),
+        peak: Velocity::new::<inch_per_hour>(n[3]),
+        speed: Velocity::new::<meter_per_second>(n.get(4).copied().unwrap_or_default()),
+    })
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = DiprCli::parse();
+    let options = ParseOptions {
+        mode: if args.lenient {
+            ParseMode::Lenient
+        } else {
+            ParseMode::Strict
+        },
+        ..Default::default()
+    };
+    let geometry = GeometryOptions {
+        datum: args.datum,
+        arc_density: match (args.arc_segments, args.max_arc_deviation) {
+            (_, Some(meters)) => ArcDensity::MaxDeviation(meters),
+            (Some(segments), None) => ArcDensity::Segments(segments),
+            (None, None) => ArcDensity::default(),
+        },
+        split_antimeridian: args.split_antimeridian,
+        skip_missing: args.skip_missing,
+    };
 
     match args.action {
-        Action::Info { input } => {
-            let dipr = read_and_convert(&input)?;
+        Action::Info {
+            input,
+            headers_only: true,
+        } => {
+            let header = parse_dipr_header(&read_input(&input)?, &options, true)?;
+            for warning in &header.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            println!("{}", header);
+        }
+        Action::Info {
+            input,
+            headers_only: false,
+        } => {
+            let dipr = read_and_convert(&input, &options)?;
             println!("{}", dipr);
         }
         Action::ToGeojson { input, skip_zeros } => {
-            let dipr = read_and_convert(&input)?;
-            convert_to_geojson(dipr, skip_zeros)?;
+            let dipr = read_and_convert(&input, &options)?;
+            convert_to_geojson(dipr, skip_zeros, &geometry, "-")?;
         }
         Action::ToShapefile {
             input,
             skip_zeros,
             output,
         } => {
-            let dipr = read_and_convert(&input)?;
-            convert_to_shapefile(dipr, skip_zeros, &output)?
+            let dipr = read_and_convert(&input, &options)?;
+            convert_to_shapefile(dipr, skip_zeros, &geometry, &output)?
+        }
+        Action::Archive {
+            input,
+            station,
+            product_code,
+            start,
+            end,
+            geojson_dir,
+            skip_zeros,
+        } => {
+            let filter = ArchiveFilter {
+                stations: station,
+                product_codes: if product_code.is_empty() {
+                    ProductType::ALL.iter().map(ProductType::code).collect()
+                } else {
+                    product_code
+                },
+                start,
+                end,
+            };
+            convert_archive(
+                &input,
+                &options,
+                filter,
+                geojson_dir.as_deref(),
+                skip_zeros,
+                &geometry,
+            )?;
+        }
+        Action::Synth {
+            output,
+            station,
+            latitude,
+            longitude,
+            capture_time,
+            radials,
+            bins,
+            bin_size,
+            range_to_first_bin,
+            rings,
+            cell,
+            band,
+            count,
+            interval,
+        } => {
+            let mut config = SynthConfig {
+                station_code: station,
+                location: GeoPoint::new(longitude, latitude),
+                num_radials: radials,
+                num_bins: bins,
+                bin_size: Length::new::<meter>(bin_size),
+                range_to_first_bin: Length::new::<meter>(range_to_first_bin),
+                patterns: rings.into_iter().chain(cell).chain(band).collect(),
+                ..Default::default()
+            };
+            if let Some(capture_time) = capture_time {
+                config.capture_time = capture_time;
+            }
+            if count == 1 {
+                write_output(&output, &config.encode()?)?;
+            } else {
+                for (idx, dipr) in config
+                    .series(count, TimeDelta::minutes(interval))?
+                    .enumerate()
+                {
+                    write_output(&format!("{output}.{:04}", idx + 1), &encode_dipr(&dipr)?)?;
+                }
+            }
         }
     };
 
@@ -0,0 +1,141 @@
+use std::ops::RangeInclusive;
+
+use chrono::{DateTime, Utc};
+
+use crate::{DiprError, ParseResult, utils::*};
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+/// Header block that begins every NEXRAD Level III message
+///
+/// See Figure 3-3 in the specification.
+pub struct MessageHeader {
+    /// Product code, which is 176 for DIPR
+    pub message_code: i16,
+    /// Moment when the message was generated
+    pub generation_time: DateTime<Utc>,
+    /// Length of the message in bytes, including this header but not the text header
+    pub length: u32,
+    /// ID of the radar that sent the message
+    pub source_id: i16,
+    /// ID of the system that the message was sent to
+    pub destination_id: i16,
+    /// Number of blocks in the message, including this header
+    pub num_blocks: i16,
+}
+
+impl MessageHeader {
+    pub(crate) const NAME: &'static str = "message header";
+    pub(crate) const LENGTH: usize = 18;
+    const TIME_RANGE: RangeInclusive<i32> = 0..=86_399;
+}
+
+/// Parse Message Header
+///
+/// Figure 3-3: Message Header Block and Table II
+pub(crate) fn message_header<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, MessageHeader> {
+    let (message_code, tail) = take_i16(input)?;
+    let (date, tail) = take_u16(tail)?;
+    let (time, tail) = take_i32(tail)?;
+    validator.check_range_inclusive(
+        MessageHeader::TIME_RANGE,
+        time,
+        "time",
+        MessageHeader::NAME,
+        tail,
+    )?;
+    let (length, tail) = take_u32(tail)?;
+    let (source_id, tail) = take_i16(tail)?;
+    let (destination_id, tail) = take_i16(tail)?;
+    let (num_blocks, tail) = take_i16(tail)?;
+
+    Ok((
+        MessageHeader {
+            message_code,
+            generation_time: julian_date_time(date, time as u32),
+            length,
+            source_id,
+            destination_id,
+            num_blocks,
+        },
+        tail,
+    ))
+}
+
+/// Encode Message Header
+pub(crate) fn encode_message_header(
+    header: &MessageHeader,
+    out: &mut Vec<u8>,
+) -> Result<(), DiprError> {
+    let (date, time) = to_julian_date_time(header.generation_time, "generation time")?;
+    put_i16(out, header.message_code);
+    put_u16(out, date);
+    put_u32(out, time);
+    put_u32(out, header.length);
+    put_i16(out, header.source_id);
+    put_i16(out, header.destination_id);
+    put_i16(out, header.num_blocks);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{ParseMode, ParseOptions};
+
+    /// DIPR message generated at noon on 1 January 2024 (day 19724)
+    const HEADER: [u8; 18] = [
+        0, 176, 0x4d, 0x0c, 0, 0, 0xa8, 0xc0, 0, 0, 0x04, 0xd2, 0, 42, 0, 0, 0, 3,
+    ];
+
+    #[test]
+    fn header_fields_are_decoded() {
+        let mut validator = Validator::new(&ParseOptions::default());
+        let (header, tail) = message_header(&HEADER, &mut validator).unwrap();
+        assert_eq!(
+            header,
+            MessageHeader {
+                message_code: 176,
+                generation_time: Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap(),
+                length: 1234,
+                source_id: 42,
+                destination_id: 0,
+                num_blocks: 3,
+            }
+        );
+        assert!(tail.is_empty());
+
+        let mut out = vec![];
+        encode_message_header(&header, &mut out).unwrap();
+        assert_eq!(out, HEADER);
+    }
+
+    #[test]
+    fn time_past_midnight_is_out_of_range() {
+        let mut input = HEADER;
+        input[4..8].copy_from_slice(&86_400i32.to_be_bytes());
+
+        let mut validator = Validator::new(&ParseOptions::default());
+        let e = message_header(&input, &mut validator).unwrap_err();
+        assert!(matches!(e.without_context(), DiprError::ValueOutOfRange(_)));
+        assert_eq!(e.context().unwrap().field, Some("time"));
+        assert_eq!(e.context().unwrap().remaining, Some(14));
+
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        let mut validator = Validator::new(&options);
+        let (header, _) = message_header(&input, &mut validator).unwrap();
+        assert_eq!(
+            header.generation_time,
+            Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap()
+        );
+        validator.locate_warnings(MessageHeader::NAME, crate::Stream::Input, input.len());
+        assert_eq!(validator.warnings().len(), 1);
+    }
+}
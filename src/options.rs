@@ -0,0 +1,109 @@
+use std::fmt::Display;
+
+use crate::ErrorContext;
+
+/// How strictly [`parse_dipr_with`](crate::parse_dipr_with) should treat input that deviates from
+/// the specification
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum ParseMode {
+    /// Fail on the first deviation from the specification, including any value outside the range
+    /// that the specification gives for it
+    #[default]
+    Strict,
+    /// Record recoverable deviations in [`PrecipRate::warnings`](crate::PrecipRate::warnings) and
+    /// keep going
+    ///
+    /// Out-of-range values are kept as they are. Values that can't be represented at all, like an
+    /// unknown operational mode, are still errors, as are counts beyond the limits in
+    /// [`ParseOptions`].
+    Lenient,
+}
+
+/// Settings that control how a DIPR product is parsed
+///
+/// The [`Default`] implementation gives the same behavior as [`parse_dipr`](crate::parse_dipr).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ParseOptions {
+    /// Whether to fail or warn on recoverable problems
+    pub mode: ParseMode,
+    /// Largest decompressed product symbology block to accept, in bytes
+    ///
+    /// Decompression stops with [`DiprError::PayloadTooLarge`](crate::DiprError::PayloadTooLarge)
+    /// once the payload grows past this size, regardless of the size that the product description
+    /// block advertises. This keeps a hostile or corrupt file from exhausting memory.
+    pub max_uncompressed_size: usize,
+    /// Largest number of radials to accept in a radial component
+    ///
+    /// This applies in every [`ParseMode`], so it bounds memory use even when out-of-range counts
+    /// are tolerated.
+    pub max_radials: usize,
+    /// Largest number of bins to accept in a radial
+    ///
+    /// Like [`ParseOptions::max_radials`], this applies in every [`ParseMode`].
+    pub max_bins: usize,
+}
+
+impl ParseOptions {
+    /// Default value of [`ParseOptions::max_uncompressed_size`]
+    ///
+    /// A DIPR product with 720 radials of 1840 bins each decompresses to a little over 5 MiB, so
+    /// this leaves plenty of headroom.
+    pub const DEFAULT_MAX_UNCOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
+    /// Default value of [`ParseOptions::max_radials`]
+    ///
+    /// The specification allows up to 800, and this leaves room for 0.1° radials.
+    pub const DEFAULT_MAX_RADIALS: usize = 3600;
+    /// Default value of [`ParseOptions::max_bins`]
+    ///
+    /// The specification allows up to 1840, and this leaves room for twice as many.
+    pub const DEFAULT_MAX_BINS: usize = 3680;
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            mode: ParseMode::default(),
+            max_uncompressed_size: Self::DEFAULT_MAX_UNCOMPRESSED_SIZE,
+            max_radials: Self::DEFAULT_MAX_RADIALS,
+            max_bins: Self::DEFAULT_MAX_BINS,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+/// Recoverable problem found while parsing in [`ParseMode::Lenient`]
+///
+/// In [`ParseMode::Strict`], each of these would have been returned as a
+/// [`DiprError`](crate::DiprError) instead.
+pub enum ParseWarning {
+    /// Decompressed product symbology block was a different size than the product description
+    /// advertised
+    UncompressedSizeMismatch {
+        /// Size given in the product description block
+        expected: u32,
+        /// Size of the decompressed payload
+        actual: usize,
+    },
+    /// Parsed value was outside its acceptable range defined in the specification
+    ValueOutOfRange {
+        /// Where the value was found
+        context: ErrorContext,
+        /// Description of the value and its acceptable range
+        message: String,
+    },
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWarning::UncompressedSizeMismatch { expected, actual } => write!(
+                f,
+                "Decompressed product symbology is {actual} bytes, but the product description says {expected}"
+            ),
+            ParseWarning::ValueOutOfRange { context, message } => {
+                write!(f, "Value out of specified range in {context}: {message}")
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{collections::BTreeMap, fmt::Display, ops::RangeInclusive};
+
+use crate::{ParseResult, utils::*};
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+/// Key/attribute pair from a generic product's parameter list (Figure E-2)
+///
+/// Attributes are written as `key=value` pairs separated by semicolons, e.g.,
+/// `name=Max Rate;type=float;unit=in/hr;value=1.25;`.
+pub struct Parameter {
+    /// Identifier of this parameter
+    pub id: String,
+    /// Attribute string exactly as it appeared in the product
+    pub raw_attributes: String,
+    /// Attributes parsed into key/value pairs
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Value of a [`Parameter`], typed according to its `type` attribute
+pub enum ParameterValue {
+    /// Value of a parameter with a `type` of `int`, `short`, or `long`
+    Int(i64),
+    /// Value of a parameter with a `type` of `float` or `double`
+    Float(f64),
+    /// Value of a parameter with any other type, or one whose value didn't parse as its type
+    Text(String),
+}
+
+impl Display for ParameterValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterValue::Int(i) => write!(f, "{}", i),
+            ParameterValue::Float(x) => write!(f, "{}", x),
+            ParameterValue::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl Parameter {
+    pub(crate) const NAME: &'static str = "parameter";
+    pub(crate) const NUM_PARAMETERS_RANGE: RangeInclusive<i32> = 0..=1000;
+
+    /// Human-readable name of this parameter from its `name` attribute, if present
+    pub fn name(&self) -> Option<&str> {
+        self.attributes.get("name").map(String::as_str)
+    }
+
+    /// Unit of this parameter's value from its `unit` attribute, if present
+    pub fn unit(&self) -> Option<&str> {
+        self.attributes.get("unit").map(String::as_str)
+    }
+
+    /// Value of this parameter from its `value` attribute, if present
+    pub fn value(&self) -> Option<ParameterValue> {
+        let value = self.attributes.get("value")?;
+        let typed = match self.attributes.get("type").map(String::as_str) {
+            Some("int" | "short" | "long") => value.trim().parse().ok().map(ParameterValue::Int),
+            Some("float" | "double") => value.trim().parse().ok().map(ParameterValue::Float),
+            _ => None,
+        };
+        Some(typed.unwrap_or_else(|| ParameterValue::Text(value.clone())))
+    }
+}
+
+/// Split a generic product attribute string into key/value pairs
+///
+/// Pairs are separated by semicolons, and each key is separated from its value by the first equals
+/// sign. Surrounding whitespace is trimmed, empty pairs are skipped, and a pair without an equals
+/// sign is kept as a key with an empty value.
+pub(crate) fn parse_attributes(attributes: &str) -> BTreeMap<String, String> {
+    attributes
+        .split(';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// Parse Parameter Data Structure (Figure E-2)
+pub(crate) fn parameter(input: &[u8]) -> ParseResult<'_, Parameter> {
+    let (id, tail) = take_string(input)?;
+    let (raw_attributes, tail) = take_string(tail)?;
+    let attributes = parse_attributes(&raw_attributes);
+    Ok((
+        Parameter {
+            id,
+            raw_attributes,
+            attributes,
+        },
+        tail,
+    ))
+}
+
+/// Parse a component's parameter list
+///
+/// The list starts with the number of parameters, which is repeated as the length of the XDR array
+/// that holds them. Only the first count is used.
+pub(crate) fn parameter_list<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, BTreeMap<String, Parameter>> {
+    let (num_parameters, tail) = take_i32(input)?;
+    validator.check_range_inclusive(
+        Parameter::NUM_PARAMETERS_RANGE,
+        num_parameters,
+        "num parameters",
+        Parameter::NAME,
+        tail,
+    )?;
+    let (_array_length, mut tail) = take_i32(tail)?;
+    let mut parameters = BTreeMap::new();
+    for _ in 0..num_parameters {
+        let tmp = parameter(tail)?;
+        parameters.insert(tmp.0.id.clone(), tmp.0);
+        tail = tmp.1;
+    }
+    Ok((parameters, tail))
+}
+
+/// Encode Parameter Data Structure (Figure E-2)
+///
+/// [`Parameter::raw_attributes`] is written as-is, so [`Parameter::attributes`] is ignored.
+pub(crate) fn encode_parameter(parameter: &Parameter, out: &mut Vec<u8>) {
+    put_string(out, &parameter.id);
+    put_string(out, &parameter.raw_attributes);
+}
+
+/// Encode a component's parameter list, including the repeated count
+pub(crate) fn encode_parameter_list(parameters: &BTreeMap<String, Parameter>, out: &mut Vec<u8>) {
+    put_i32(out, parameters.len() as i32);
+    put_i32(out, parameters.len() as i32);
+    for parameter in parameters.values() {
+        encode_parameter(parameter, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameter_with(attributes: &str) -> Parameter {
+        Parameter {
+            id: "test".to_string(),
+            raw_attributes: attributes.to_string(),
+            attributes: parse_attributes(attributes),
+        }
+    }
+
+    #[test]
+    fn attributes_are_split_into_pairs() {
+        let attributes = parse_attributes(" name = Max Rate ;unit=in/hr;;flagged; expr=a=b;");
+        let expected = [
+            ("expr", "a=b"),
+            ("flagged", ""),
+            ("name", "Max Rate"),
+            ("unit", "in/hr"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert_eq!(attributes, BTreeMap::from(expected));
+        assert!(parse_attributes("").is_empty());
+        assert!(parse_attributes(" ; ;").is_empty());
+    }
+
+    #[test]
+    fn values_are_typed_by_their_type_attribute() {
+        let cases = [
+            ("type=int;value=-3;", ParameterValue::Int(-3)),
+            ("type=short;value= 7 ;", ParameterValue::Int(7)),
+            (
+                "type=long;value=9000000000;",
+                ParameterValue::Int(9_000_000_000),
+            ),
+            ("type=float;value=1.25;", ParameterValue::Float(1.25)),
+            ("type=double;value=-0.5;", ParameterValue::Float(-0.5)),
+            (
+                "type=string;value=KOAX;",
+                ParameterValue::Text("KOAX".into()),
+            ),
+            ("value=12;", ParameterValue::Text("12".into())),
+            ("type=int;value=1.5;", ParameterValue::Text("1.5".into())),
+        ];
+        for (attributes, expected) in cases {
+            assert_eq!(
+                parameter_with(attributes).value(),
+                Some(expected),
+                "{attributes}"
+            );
+        }
+        assert_eq!(parameter_with("type=int;").value(), None);
+    }
+
+    #[test]
+    fn parameter_lists_round_trip() {
+        let parameters: BTreeMap<_, _> = ["a", "b"]
+            .into_iter()
+            .map(|id| {
+                let parameter = Parameter {
+                    id: id.to_string(),
+                    ..parameter_with("name=Thing;value=1;")
+                };
+                (parameter.id.clone(), parameter)
+            })
+            .collect();
+        let mut out = vec![];
+        encode_parameter_list(&parameters, &mut out);
+        let mut validator = Validator::new(&crate::ParseOptions::default());
+        let (decoded, tail) = parameter_list(&out, &mut validator).unwrap();
+        assert!(tail.is_empty());
+        assert_eq!(decoded, parameters);
+    }
+}
@@ -1,11 +1,16 @@
 use std::{fmt::Display, ops::RangeInclusive};
 
+use chrono::{DateTime, Utc};
 use geo::Point;
-use uom::si::f32::Velocity;
+use uom::si::{
+    f32::{Length, Velocity},
+    length::foot,
+};
 
-use crate::{DiprError, ParseResult, inch_per_hour, utils::*};
+use crate::{DiprError, ParseResult, ProductType, Thresholds, inch_per_hour, utils::*};
 
 #[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+/// Condition of the radar station when the product was generated
 pub enum OperationalMode {
     Maintenance,
     CleanAir,
@@ -22,6 +27,16 @@ impl Display for OperationalMode {
     }
 }
 
+impl From<OperationalMode> for i16 {
+    fn from(value: OperationalMode) -> Self {
+        match value {
+            OperationalMode::Maintenance => 0,
+            OperationalMode::CleanAir => 1,
+            OperationalMode::Precipitation => 2,
+        }
+    }
+}
+
 impl TryFrom<i16> for OperationalMode {
     type Error = DiprError;
 
@@ -38,91 +53,399 @@ impl TryFrom<i16> for OperationalMode {
     }
 }
 
-pub(crate) struct ProductDescription {
-    pub(crate) location: Point<f32>,
-    pub(crate) operational_mode: OperationalMode,
-    pub(crate) precip_detected: bool,
-    pub(crate) max_precip_rate: Velocity,
-    pub(crate) uncompressed_size: u32,
+#[derive(Clone, Debug, PartialEq)]
+/// Every field of the Product Description Block
+///
+/// See Figure 3-6 (Sheet 6) and Table V in the specification. Halfword numbers in the field
+/// documentation count from the start of the message header, as they do in the specification.
+pub struct ProductDescription {
+    /// Longitude/latitude coordinates of the radar station in degrees (halfwords 11-14)
+    pub location: Point<f64>,
+    /// Height of the radar station above mean sea level (halfword 15)
+    pub height: Length,
+    /// Product code, which is 176 for DIPR (halfword 16)
+    ///
+    /// See [`ProductDescription::product_type`] for the product that this stands for.
+    pub product_code: i16,
+    /// Condition of the radar station (halfword 17)
+    pub operational_mode: OperationalMode,
+    /// Volume coverage pattern (VCP) number describing the scan strategy, e.g., 212 (halfword 18)
+    pub volume_coverage_pattern: i16,
+    /// Sequence number of the request that generated the product (halfword 19)
+    pub sequence_number: i16,
+    /// Incrementing counter to disambiguate scans, between 1 and 80 inclusive (halfword 20)
+    pub volume_scan_number: i16,
+    /// Moment when the volume scan began (halfwords 21-23)
+    pub volume_scan_time: DateTime<Utc>,
+    /// Moment when the product was generated (halfwords 24-26)
+    pub generation_time: DateTime<Utc>,
+    /// Elevation number within the volume scan, or 0 for volume products (halfword 29)
+    pub elevation_number: i16,
+    /// Raw product-dependent parameters 1 through 10 (halfwords 27, 28, 30, and 47-53)
+    ///
+    /// The meanings of these values differ by product. For DIPR, the most useful ones are
+    /// decoded into [`ProductDescription::precip_detected`],
+    /// [`ProductDescription::max_precip_rate`], [`ProductDescription::compression_method`], and
+    /// [`ProductDescription::uncompressed_size`].
+    pub product_dependent: [i16; 10],
+    /// Raw data level threshold values (halfwords 31-46)
+    ///
+    /// For digital radial products, these are decoded into
+    /// [`ProductDescription::thresholds`].
+    pub data_level_thresholds: [i16; 16],
+    /// Version of the product format (high byte of halfword 54)
+    pub version: u8,
+    /// Whether the spot blanking feature was enabled (low byte of halfword 54)
+    pub spot_blank: bool,
+    /// Offset from the start of the message header to the product symbology block in halfwords,
+    /// or 0 if absent (halfwords 55-56)
+    pub symbology_offset: u32,
+    /// Offset from the start of the message header to the graphic alphanumeric block in
+    /// halfwords, or 0 if absent (halfwords 57-58)
+    pub graphic_offset: u32,
+    /// Offset from the start of the message header to the tabular alphanumeric block in
+    /// halfwords, or 0 if absent (halfwords 59-60)
+    pub tabular_offset: u32,
+    /// Whether the radar station measured any precipitation anywhere in its coverage area (high
+    /// byte of product-dependent parameter 3)
+    pub precip_detected: bool,
+    /// Highest precipitation rate in the product (product-dependent parameter 4)
+    ///
+    /// This is only meaningful for DIPR. Other products use the parameter for something else.
+    pub max_precip_rate: Velocity,
+    /// Method used to compress the product symbology block, where 0 means none and 1 means bzip2
+    /// (product-dependent parameter 8)
+    pub compression_method: i16,
+    /// Size of the product symbology block in bytes after decompression (product-dependent
+    /// parameters 9 and 10)
+    pub uncompressed_size: u32,
 }
 
 impl ProductDescription {
-    const NAME: &'static str = "product description block";
+    pub(crate) const NAME: &'static str = "product description block";
+    pub(crate) const LENGTH: usize = 102;
+    /// Value of [`ProductDescription::compression_method`] for an uncompressed product
+    pub(crate) const NO_COMPRESSION: i16 = 0;
+    /// Value of [`ProductDescription::compression_method`] for a bzip2-compressed product
+    pub(crate) const BZIP2_COMPRESSION: i16 = 1;
+    /// Offset of [`ProductDescription::product_code`] from the start of the block
+    pub(crate) const PRODUCT_CODE_OFFSET: usize = 12;
+    /// Offset of [`ProductDescription::volume_scan_number`] from the start of the block
+    pub(crate) const VOLUME_SCAN_NUMBER_OFFSET: usize = 18;
     const BLOCK_DIVIDER_VALUE: i16 = -1;
-    const LATITUDE_RANGE: RangeInclusive<i32> = -90_000..=90_000;
-    const LONGITUDE_RANGE: RangeInclusive<i32> = -180_000..=180_000;
+    pub(crate) const LATITUDE_RANGE: RangeInclusive<i32> = -90_000..=90_000;
+    pub(crate) const LONGITUDE_RANGE: RangeInclusive<i32> = -180_000..=180_000;
     const OPERATIONAL_MODE_RANGE: RangeInclusive<i16> = 0..=2;
     const PRECIP_DETECTED_RANGE: RangeInclusive<i8> = 0..=1;
+
+    /// Product that [`ProductDescription::product_code`] stands for, or [`None`] if this crate
+    /// doesn't support it
+    pub fn product_type(&self) -> Option<ProductType> {
+        ProductType::from_code(self.product_code)
+    }
+
+    /// Mapping from data levels to values given by
+    /// [`ProductDescription::data_level_thresholds`], or [`None`] if this isn't a digital radial
+    /// product
+    pub fn thresholds(&self) -> Option<Thresholds> {
+        Thresholds::from_halfwords(self.product_type()?, &self.data_level_thresholds)
+    }
 }
 
 /// Parse Product Description
 ///
 /// Figure 3-6: Graphic Product Message (Sheet 6) and Table V
-pub(crate) fn product_description(input: &[u8]) -> ParseResult<ProductDescription> {
+pub(crate) fn product_description<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, ProductDescription> {
     let (block_divider, tail) = take_i16(input)?;
     check_value(
         ProductDescription::BLOCK_DIVIDER_VALUE,
         block_divider,
         "block divider",
         ProductDescription::NAME,
+        tail,
     )?;
 
     let (latitude_int, tail) = take_i32(tail)?;
-    check_range_inclusive(
+    validator.check_range_inclusive(
         ProductDescription::LATITUDE_RANGE,
         latitude_int,
         "latitude",
         ProductDescription::NAME,
+        tail,
     )?;
 
     let (longitude_int, tail) = take_i32(tail)?;
-    check_range_inclusive(
+    validator.check_range_inclusive(
         ProductDescription::LONGITUDE_RANGE,
         longitude_int,
         "longitude",
         ProductDescription::NAME,
+        tail,
     )?;
 
-    let (_, tail) = take_bytes(tail, 4)?;
+    let (height, tail) = take_i16(tail)?;
+    let (product_code, tail) = take_i16(tail)?;
 
     let (operational_mode_int, tail) = take_i16(tail)?;
-    check_range_inclusive(
+    validator.check_range_inclusive(
         ProductDescription::OPERATIONAL_MODE_RANGE,
         operational_mode_int,
         "operational mode",
         ProductDescription::NAME,
+        tail,
     )?;
 
-    let (_, tail) = take_bytes(tail, 24)?;
+    let (volume_coverage_pattern, tail) = take_i16(tail)?;
+    let (sequence_number, tail) = take_i16(tail)?;
+    let (volume_scan_number, tail) = take_i16(tail)?;
+    let (volume_scan_date, tail) = take_u16(tail)?;
+    let (volume_scan_seconds, tail) = take_u32(tail)?;
+    let (generation_date, tail) = take_u16(tail)?;
+    let (generation_seconds, tail) = take_u32(tail)?;
 
-    let (precip_detected_int, tail) = take_i8(tail)?;
-    check_range_inclusive(
+    let mut product_dependent = [0; 10];
+    let (p1, tail) = take_i16(tail)?;
+    let (p2, tail) = take_i16(tail)?;
+    let (elevation_number, tail) = take_i16(tail)?;
+    let (precip_detected_int, after_flag) = take_i8(tail)?;
+    validator.check_range_inclusive(
         ProductDescription::PRECIP_DETECTED_RANGE,
         precip_detected_int,
         "precipitation detected",
         ProductDescription::NAME,
+        after_flag,
     )?;
+    let (p3, tail) = take_i16(tail)?;
+    product_dependent[..3].copy_from_slice(&[p1, p2, p3]);
+
+    let mut data_level_thresholds = [0; 16];
+    let mut tail = tail;
+    for threshold in data_level_thresholds.iter_mut() {
+        (*threshold, tail) = take_i16(tail)?;
+    }
 
-    let (_, tail) = take_bytes(tail, 33)?;
-    let (max_precip_rate, tail) = take_i16(tail)?;
-    let (_, tail) = take_bytes(tail, 8)?;
-    let (uncompressed_size, tail) = take_i32(tail)?;
-    let (_, tail) = take_bytes(tail, 14)?;
+    for param in product_dependent[3..].iter_mut() {
+        (*param, tail) = take_i16(tail)?;
+    }
+
+    let (version, tail) = take_u8(tail)?;
+    let (spot_blank, tail) = take_u8(tail)?;
+    let (symbology_offset, tail) = take_u32(tail)?;
+    let (graphic_offset, tail) = take_u32(tail)?;
+    let (tabular_offset, tail) = take_u32(tail)?;
 
-    let location = Point::new(longitude_int as f32 / 1000., latitude_int as f32 / 1000.);
+    let location = Point::new(longitude_int as f64 / 1000., latitude_int as f64 / 1000.);
+    let height = Length::new::<foot>(height as f32);
     let operational_mode = operational_mode_int.try_into()?;
+    let volume_scan_time = julian_date_time(volume_scan_date, volume_scan_seconds);
+    let generation_time = julian_date_time(generation_date, generation_seconds);
     let precip_detected = precip_detected_int != 0;
-    let max_precip_rate = Velocity::new::<inch_per_hour>(max_precip_rate as f32 / 1000.);
-    let uncompressed_size = uncompressed_size as u32;
+    let max_precip_rate = Velocity::new::<inch_per_hour>(product_dependent[3] as f32 / 1000.);
+    let compression_method = product_dependent[7];
+    let uncompressed_size =
+        ((product_dependent[8] as u16 as u32) << 16) | product_dependent[9] as u16 as u32;
 
     Ok((
         ProductDescription {
             location,
+            height,
+            product_code,
             operational_mode,
+            volume_coverage_pattern,
+            sequence_number,
+            volume_scan_number,
+            volume_scan_time,
+            generation_time,
+            elevation_number,
+            product_dependent,
+            data_level_thresholds,
+            version,
+            spot_blank: spot_blank != 0,
+            symbology_offset,
+            graphic_offset,
+            tabular_offset,
             precip_detected,
             max_precip_rate,
+            compression_method,
             uncompressed_size,
         },
         tail,
     ))
 }
+
+/// Encode Product Description
+///
+/// The summarized fields, e.g., [`ProductDescription::max_precip_rate`], take precedence over the
+/// product-dependent parameters that they were decoded from.
+pub(crate) fn encode_product_description(
+    description: &ProductDescription,
+    out: &mut Vec<u8>,
+) -> Result<(), DiprError> {
+    let mut product_dependent = description.product_dependent;
+    let [_, low] = product_dependent[2].to_be_bytes();
+    product_dependent[2] = i16::from_be_bytes([description.precip_detected as u8, low]);
+    product_dependent[3] =
+        (description.max_precip_rate.get::<inch_per_hour>() * 1000.).round() as i16;
+    product_dependent[7] = description.compression_method;
+    product_dependent[8] = (description.uncompressed_size >> 16) as u16 as i16;
+    product_dependent[9] = description.uncompressed_size as u16 as i16;
+
+    let (volume_scan_date, volume_scan_seconds) =
+        to_julian_date_time(description.volume_scan_time, "volume scan time")?;
+    let (generation_date, generation_seconds) =
+        to_julian_date_time(description.generation_time, "generation time")?;
+
+    put_i16(out, ProductDescription::BLOCK_DIVIDER_VALUE);
+    put_i32(out, (description.location.y() * 1000.).round() as i32);
+    put_i32(out, (description.location.x() * 1000.).round() as i32);
+    put_i16(out, description.height.get::<foot>().round() as i16);
+    put_i16(out, description.product_code);
+    put_i16(out, description.operational_mode.into());
+    put_i16(out, description.volume_coverage_pattern);
+    put_i16(out, description.sequence_number);
+    put_i16(out, description.volume_scan_number);
+    put_u16(out, volume_scan_date);
+    put_u32(out, volume_scan_seconds);
+    put_u16(out, generation_date);
+    put_u32(out, generation_seconds);
+    put_i16(out, product_dependent[0]);
+    put_i16(out, product_dependent[1]);
+    put_i16(out, description.elevation_number);
+    put_i16(out, product_dependent[2]);
+    for threshold in description.data_level_thresholds {
+        put_i16(out, threshold);
+    }
+    for param in &product_dependent[3..] {
+        put_i16(out, *param);
+    }
+    out.push(description.version);
+    out.push(description.spot_blank as u8);
+    put_u32(out, description.symbology_offset);
+    put_u32(out, description.graphic_offset);
+    put_u32(out, description.tabular_offset);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{ParseMode, ParseOptions};
+
+    /// Block of a DIPR product from KOAX at noon on 1 January 2024, laid out field by field
+    fn block(operational_mode: i16, precip_detected: u8) -> Vec<u8> {
+        let mut out = vec![];
+        put_i16(&mut out, -1);
+        put_i32(&mut out, 41_320);
+        put_i32(&mut out, -96_367);
+        put_i16(&mut out, 1148);
+        put_i16(&mut out, 176);
+        put_i16(&mut out, operational_mode);
+        put_i16(&mut out, 212);
+        put_i16(&mut out, 7);
+        put_i16(&mut out, 42);
+        put_u16(&mut out, 19724);
+        put_u32(&mut out, 43_200);
+        put_u16(&mut out, 19724);
+        put_u32(&mut out, 43_260);
+        put_i16(&mut out, 11);
+        put_i16(&mut out, 12);
+        put_i16(&mut out, 0);
+        out.extend([precip_detected, 13]);
+        for threshold in 0..16 {
+            put_i16(&mut out, threshold);
+        }
+        for param in [2500, 15, 16, 17, 1, 0x0001, 0x2345] {
+            put_i16(&mut out, param);
+        }
+        out.extend([3, 1]);
+        put_u32(&mut out, 60);
+        put_u32(&mut out, 0);
+        put_u32(&mut out, 0);
+        out
+    }
+
+    #[test]
+    fn every_field_is_decoded() {
+        let input = block(2, 1);
+        assert_eq!(input.len(), ProductDescription::LENGTH);
+        let mut validator = Validator::new(&ParseOptions::default());
+        let (description, tail) = product_description(&input, &mut validator).unwrap();
+        assert!(tail.is_empty());
+
+        let noon = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(description.location, Point::new(-96.367, 41.32));
+        assert_eq!(description.height, Length::new::<foot>(1148.));
+        assert_eq!(description.product_type(), Some(ProductType::PrecipRate));
+        assert_eq!(description.operational_mode, OperationalMode::Precipitation);
+        assert_eq!(description.volume_coverage_pattern, 212);
+        assert_eq!(description.sequence_number, 7);
+        assert_eq!(description.volume_scan_number, 42);
+        assert_eq!(description.volume_scan_time, noon);
+        assert_eq!(
+            description.generation_time,
+            noon + chrono::TimeDelta::minutes(1)
+        );
+        assert_eq!(description.elevation_number, 0);
+        assert_eq!(
+            description.product_dependent,
+            [11, 12, 0x010d, 2500, 15, 16, 17, 1, 0x0001, 0x2345]
+        );
+        assert_eq!(
+            description.data_level_thresholds,
+            std::array::from_fn(|i| i as i16)
+        );
+        assert_eq!(description.version, 3);
+        assert!(description.spot_blank);
+        assert_eq!(description.symbology_offset, 60);
+        assert_eq!(description.graphic_offset, 0);
+        assert_eq!(description.tabular_offset, 0);
+        assert!(description.precip_detected);
+        assert_eq!(
+            description.max_precip_rate,
+            Velocity::new::<inch_per_hour>(2.5)
+        );
+        assert_eq!(
+            description.compression_method,
+            ProductDescription::BZIP2_COMPRESSION
+        );
+        assert_eq!(description.uncompressed_size, 0x0001_2345);
+        assert_eq!(description.thresholds(), None);
+
+        let mut out = vec![];
+        encode_product_description(&description, &mut out).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn precip_flag_out_of_range_is_located() {
+        let input = block(2, 2);
+        let mut validator = Validator::new(&ParseOptions::default());
+        let e = product_description(&input, &mut validator).unwrap_err();
+        let context = e.context().unwrap();
+        assert_eq!(context.field, Some("precipitation detected"));
+        // the flag is the high byte of halfword 30, 40 bytes into the block
+        assert_eq!(context.remaining, Some(ProductDescription::LENGTH - 40));
+    }
+
+    #[test]
+    fn unknown_operational_mode_is_an_error_in_every_mode() {
+        let input = block(3, 1);
+        for mode in [ParseMode::Strict, ParseMode::Lenient] {
+            let options = ParseOptions {
+                mode,
+                ..Default::default()
+            };
+            let mut validator = Validator::new(&options);
+            let e = product_description(&input, &mut validator).unwrap_err();
+            assert!(
+                matches!(e.without_context(), DiprError::ValueOutOfRange(_)),
+                "{e:?}"
+            );
+        }
+    }
+}
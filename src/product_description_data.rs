@@ -0,0 +1,347 @@
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use chrono::{DateTime, Utc};
+use geo::Point;
+use uom::si::{
+    angle::degree,
+    f32::{Angle, Length},
+    length::meter,
+};
+
+use crate::{
+    DiprError, OperationalMode, ParseResult, ProductDescription, ProductType,
+    parameters::{Parameter, encode_parameter, parameter},
+    utils::*,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+/// Metadata that describes a generic product from inside its product symbology block
+///
+/// See Figure E-1 in the specification. Much of this repeats the
+/// [`ProductDescription`](crate::ProductDescription), but with more precision.
+pub struct ProductDescriptionData {
+    /// Short name of the product, e.g., `DPR`
+    pub name: String,
+    /// Longer description of the product
+    pub description: String,
+    /// Product code, which is 176 for DIPR
+    pub product_code: i32,
+    /// Generic product type
+    pub product_type: i32,
+    /// Moment when the product was generated
+    pub generation_time: DateTime<Utc>,
+    /// Name of the radar station, e.g., `KOAX`
+    pub radar_name: String,
+    /// Longitude/latitude coordinates of the radar station in degrees
+    ///
+    /// These are stored in single precision but widened to match
+    /// [`ProductDescription::location`](crate::ProductDescription::location).
+    pub radar_location: Point<f64>,
+    /// Height of the radar station above mean sea level
+    pub radar_height: Length,
+    /// Moment when the volume scan began
+    pub volume_scan_start_time: DateTime<Utc>,
+    /// Moment when the volume scan ended
+    pub volume_scan_end_time: DateTime<Utc>,
+    /// Elevation angle of the scan, or 0 for volume products like DIPR
+    pub elevation_angle: Angle,
+    /// Incrementing counter to disambiguate scans, between 1 and 80 inclusive
+    pub volume_scan_number: i32,
+    /// Condition of the radar station
+    pub operational_mode: OperationalMode,
+    /// Volume coverage pattern (VCP) number describing the scan strategy
+    pub volume_coverage_pattern: i32,
+    /// Elevation number within the volume scan, or 0 for volume products
+    pub elevation_number: i32,
+    /// Method used to compress the product's components, where 0 means none
+    pub compression_type: i32,
+    /// Size of the product's components after decompression in bytes
+    pub decompressed_size: i32,
+    /// Product parameters keyed by [`Parameter::id`]
+    pub parameters: BTreeMap<String, Parameter>,
+}
+
+impl ProductDescriptionData {
+    pub(crate) const NAME: &'static str = "product description data";
+    pub(crate) const SCAN_NUMBER_RANGE: RangeInclusive<i32> = 1..=80;
+    const OPERATIONAL_MODE_RANGE: RangeInclusive<i32> = 0..=2;
+
+    /// Fill in the metadata of a product of type `product_type` that doesn't have this structure,
+    /// like a digital radial product, from its product description block
+    ///
+    /// The volume scan end time isn't known, so it's the same as the start time.
+    pub(crate) fn from_product_description(
+        product_description: &ProductDescription,
+        product_type: ProductType,
+        radar_name: &str,
+        elevation_angle: Angle,
+    ) -> Self {
+        ProductDescriptionData {
+            name: product_type.mnemonic().to_string(),
+            description: String::new(),
+            product_code: product_description.product_code.into(),
+            product_type: 0,
+            generation_time: product_description.generation_time,
+            radar_name: radar_name.to_string(),
+            radar_location: product_description.location,
+            radar_height: product_description.height,
+            volume_scan_start_time: product_description.volume_scan_time,
+            volume_scan_end_time: product_description.volume_scan_time,
+            elevation_angle,
+            volume_scan_number: product_description.volume_scan_number.into(),
+            operational_mode: product_description.operational_mode,
+            volume_coverage_pattern: product_description.volume_coverage_pattern.into(),
+            elevation_number: product_description.elevation_number.into(),
+            compression_type: product_description.compression_method.into(),
+            decompressed_size: product_description.uncompressed_size as i32,
+            parameters: BTreeMap::new(),
+        }
+    }
+}
+
+fn timestamp(seconds: u32) -> Result<DateTime<Utc>, DiprError> {
+    DateTime::from_timestamp(seconds as i64, 0).ok_or(DiprError::InvalidCaptureTime(seconds))
+}
+
+/// Parse Product Description Data Structure (Figure E-1)
+pub(crate) fn product_description_data<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, ProductDescriptionData> {
+    let (name, tail) = take_string(input)?;
+    let (description, tail) = take_string(tail)?;
+    let (product_code, tail) = take_i32(tail)?;
+    let (product_type, tail) = take_i32(tail)?;
+    let (generation_time, tail) = take_u32(tail)?;
+    let (radar_name, tail) = take_string(tail)?;
+    let (radar_latitude, tail) = take_float(tail)?;
+    let (radar_longitude, tail) = take_float(tail)?;
+    let (radar_height, tail) = take_float(tail)?;
+    let (volume_scan_start_time, tail) = take_u32(tail)?;
+    let (volume_scan_end_time, tail) = take_u32(tail)?;
+    let (elevation_angle, tail) = take_float(tail)?;
+    let (volume_scan_number, tail) = take_i32(tail)?;
+    validator.check_range_inclusive(
+        ProductDescriptionData::SCAN_NUMBER_RANGE,
+        volume_scan_number,
+        "scan number",
+        ProductDescriptionData::NAME,
+        tail,
+    )?;
+    let (operational_mode, tail) = take_i32(tail)?;
+    validator.check_range_inclusive(
+        ProductDescriptionData::OPERATIONAL_MODE_RANGE,
+        operational_mode,
+        "operational mode",
+        ProductDescriptionData::NAME,
+        tail,
+    )?;
+    let (volume_coverage_pattern, tail) = take_i32(tail)?;
+    let (elevation_number, tail) = take_i32(tail)?;
+    let (compression_type, tail) = take_i32(tail)?;
+    let (decompressed_size, tail) = take_i32(tail)?;
+
+    let (num_parameters, mut tail) = take_i32(tail)?;
+    validator.check_range_inclusive(
+        Parameter::NUM_PARAMETERS_RANGE,
+        num_parameters,
+        "num parameters",
+        ProductDescriptionData::NAME,
+        tail,
+    )?;
+    let mut parameters = BTreeMap::new();
+    for _ in 0..num_parameters {
+        let tmp = parameter(tail).map_err(|e| {
+            e.with_context(|c| {
+                if c.section.is_empty() {
+                    c.section = Parameter::NAME;
+                }
+            })
+        })?;
+        parameters.insert(tmp.0.id.clone(), tmp.0);
+        tail = tmp.1;
+    }
+
+    Ok((
+        ProductDescriptionData {
+            name,
+            description,
+            product_code,
+            product_type,
+            generation_time: timestamp(generation_time)?,
+            radar_name,
+            radar_location: Point::new(radar_longitude as f64, radar_latitude as f64),
+            radar_height: Length::new::<meter>(radar_height),
+            volume_scan_start_time: timestamp(volume_scan_start_time)?,
+            volume_scan_end_time: timestamp(volume_scan_end_time)?,
+            elevation_angle: Angle::new::<degree>(elevation_angle),
+            volume_scan_number,
+            operational_mode: (operational_mode as i16).try_into()?,
+            volume_coverage_pattern,
+            elevation_number,
+            compression_type,
+            decompressed_size,
+            parameters,
+        },
+        tail,
+    ))
+}
+
+/// Encode Product Description Data Structure (Figure E-1)
+pub(crate) fn encode_product_description_data(
+    data: &ProductDescriptionData,
+    out: &mut Vec<u8>,
+) -> Result<(), DiprError> {
+    put_string(out, &data.name);
+    put_string(out, &data.description);
+    put_i32(out, data.product_code);
+    put_i32(out, data.product_type);
+    put_u32(
+        out,
+        to_unix_seconds(data.generation_time, "generation time")?,
+    );
+    put_string(out, &data.radar_name);
+    put_float(out, data.radar_location.y() as f32);
+    put_float(out, data.radar_location.x() as f32);
+    put_float(out, data.radar_height.get::<meter>());
+    put_u32(
+        out,
+        to_unix_seconds(data.volume_scan_start_time, "volume scan start time")?,
+    );
+    put_u32(
+        out,
+        to_unix_seconds(data.volume_scan_end_time, "volume scan end time")?,
+    );
+    put_float(out, data.elevation_angle.get::<degree>());
+    put_i32(out, data.volume_scan_number);
+    put_i32(out, i16::from(data.operational_mode).into());
+    put_i32(out, data.volume_coverage_pattern);
+    put_i32(out, data.elevation_number);
+    put_i32(out, data.compression_type);
+    put_i32(out, data.decompressed_size);
+    put_i32(out, data.parameters.len() as i32);
+    for parameter in data.parameters.values() {
+        encode_parameter(parameter, out);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{ParseOptions, parameters::ParameterValue};
+
+    /// Structure of a DPR product from KOAX at noon on 1 January 2024, laid out field by field
+    fn structure(volume_scan_number: i32, parameters: &[(&str, &str)]) -> Vec<u8> {
+        let mut out = vec![];
+        put_string(&mut out, "DPR");
+        put_string(&mut out, "Digital Instantaneous Precipitation Rate");
+        put_i32(&mut out, 176);
+        put_i32(&mut out, 1);
+        put_u32(&mut out, 1_704_110_460);
+        put_string(&mut out, "KOAX");
+        put_float(&mut out, 41.32);
+        put_float(&mut out, -96.367);
+        put_float(&mut out, 350.);
+        put_u32(&mut out, 1_704_110_400);
+        put_u32(&mut out, 1_704_110_700);
+        put_float(&mut out, 0.5);
+        put_i32(&mut out, volume_scan_number);
+        put_i32(&mut out, 2);
+        put_i32(&mut out, 212);
+        put_i32(&mut out, 0);
+        put_i32(&mut out, 1);
+        put_i32(&mut out, 0x0001_2345);
+        put_i32(&mut out, parameters.len() as i32);
+        for (id, attributes) in parameters {
+            put_string(&mut out, id);
+            put_string(&mut out, attributes);
+        }
+        out
+    }
+
+    #[test]
+    fn every_field_is_decoded() {
+        let input = structure(
+            42,
+            &[
+                ("bins", "type=int;value= 920 ;"),
+                (
+                    "max_rate",
+                    "name=Max Rate;type=float;unit=in/hr;value=1.25;",
+                ),
+            ],
+        );
+        let mut validator = Validator::new(&ParseOptions::default());
+        let (data, tail) = product_description_data(&input, &mut validator).unwrap();
+        assert!(tail.is_empty());
+
+        let noon = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(data.name, "DPR");
+        assert_eq!(data.description, "Digital Instantaneous Precipitation Rate");
+        assert_eq!(data.product_code, 176);
+        assert_eq!(data.product_type, 1);
+        assert_eq!(data.generation_time, noon + chrono::TimeDelta::minutes(1));
+        assert_eq!(data.radar_name, "KOAX");
+        assert_eq!(
+            data.radar_location,
+            Point::new(-96.367f32 as f64, 41.32f32 as f64)
+        );
+        assert_eq!(data.radar_height, Length::new::<meter>(350.));
+        assert_eq!(data.volume_scan_start_time, noon);
+        assert_eq!(
+            data.volume_scan_end_time,
+            noon + chrono::TimeDelta::minutes(5)
+        );
+        assert_eq!(data.elevation_angle, Angle::new::<degree>(0.5));
+        assert_eq!(data.volume_scan_number, 42);
+        assert_eq!(data.operational_mode, OperationalMode::Precipitation);
+        assert_eq!(data.volume_coverage_pattern, 212);
+        assert_eq!(data.elevation_number, 0);
+        assert_eq!(data.compression_type, 1);
+        assert_eq!(data.decompressed_size, 0x0001_2345);
+
+        assert_eq!(
+            data.parameters.keys().collect::<Vec<_>>(),
+            ["bins", "max_rate"]
+        );
+        let max_rate = &data.parameters["max_rate"];
+        assert_eq!(max_rate.name(), Some("Max Rate"));
+        assert_eq!(max_rate.unit(), Some("in/hr"));
+        assert_eq!(max_rate.value(), Some(ParameterValue::Float(1.25)));
+        assert_eq!(
+            data.parameters["bins"].value(),
+            Some(ParameterValue::Int(920))
+        );
+
+        let mut out = vec![];
+        encode_product_description_data(&data, &mut out).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn scan_number_out_of_range_is_located() {
+        let input = structure(81, &[]);
+        let mut validator = Validator::new(&ParseOptions::default());
+        let e = product_description_data(&input, &mut validator).unwrap_err();
+        let context = e.context().unwrap();
+        assert_eq!(context.field, Some("scan number"));
+        // the scan number is followed by six more four-byte fields when there are no parameters
+        assert_eq!(context.remaining, Some(4 + 6 * 4));
+    }
+
+    #[test]
+    fn truncated_parameters_are_blamed_on_the_parameter() {
+        let mut input = structure(42, &[("max_rate", "value=1.25;")]);
+        input.truncate(input.len() - 4);
+        let mut validator = Validator::new(&ParseOptions::default());
+        let e = product_description_data(&input, &mut validator).unwrap_err();
+        assert_eq!(e.context().unwrap().section, Parameter::NAME);
+        assert!(
+            matches!(e.without_context(), DiprError::Truncated { .. }),
+            "{e:?}"
+        );
+    }
+}
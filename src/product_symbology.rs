@@ -1,114 +1,244 @@
 use std::ops::RangeInclusive;
 
 use chrono::{DateTime, Utc};
-use uom::si::{f32::Length, length::meter};
+use uom::si::{
+    angle::degree,
+    f32::{Angle, Length},
+};
 
 use crate::{
-    DiprError, ParseResult,
-    radials::{Radial, radial},
+    DiprError, ParseResult, ProductDescription, ProductType,
+    components::{
+        Component, RadialComponent, component, encode_component, encode_radial_component,
+    },
+    digital_radials::digital_radials,
+    product_description_data::{
+        ProductDescriptionData, encode_product_description_data, product_description_data,
+    },
+    radials::Radial,
     utils::*,
 };
 
 pub(crate) struct ProductSymbology {
     pub(crate) range_to_first_bin: Length,
     pub(crate) bin_size: Length,
-    pub(crate) scan_number: u8,
     pub(crate) capture_time: DateTime<Utc>,
     pub(crate) radials: Vec<Radial>,
+    pub(crate) radial_component: RadialComponent,
+    /// Number of `components` that precede `radial_component`
+    pub(crate) radial_component_index: usize,
+    pub(crate) components: Vec<Component>,
+    pub(crate) description_data: ProductDescriptionData,
 }
 
 impl ProductSymbology {
-    const NAME: &'static str = "product symbology";
-    const SCAN_NUMBER_RANGE: RangeInclusive<i32> = 1..=80;
-    const RADIAL_COMPONENT_TYPE_VALUE: i32 = 1;
-    const BIN_SIZE_RANGE: RangeInclusive<f32> = (0.)..=1000.;
-    // It seems like the specified range may be incorrect for actual DIPR files
-    // const RANGE_TO_FIRST_BIN_RANGE: RangeInclusive<f32> = (1000.)..=460000.;
-    const NUM_RADIALS_RANGE: RangeInclusive<i32> = 0..=800;
+    pub(crate) const NAME: &'static str = "product symbology";
+    const NUM_COMPONENTS_RANGE: RangeInclusive<i32> = 1..=1000;
+    const BLOCK_DIVIDER: i16 = -1;
+    const BLOCK_ID: i16 = 1;
+    const NUM_LAYERS: i16 = 1;
+    const LAYER_DIVIDER: i16 = -1;
+    const HEADER_LENGTH: usize = 16;
+    /// Packet code of the generic data packet (Figure 3-15c)
+    const GENERIC_PACKET_CODE: i16 = 28;
+    const PACKET_HEADER_LENGTH: usize = 8;
 }
 
-pub(crate) fn product_symbology(input: &[u8]) -> ParseResult<ProductSymbology> {
-    // header (Figure 3-6, Sheet 7)
-    let (_, tail) = take_bytes(input, 16)?;
-
-    // another header (Figure 3-15c)
-    let (_, tail) = take_bytes(tail, 8)?;
-
-    // Product Description Data Structure header (Figure E-1)
-    let (_, tail) = take_string(tail)?; // name
-    let (_, tail) = take_string(tail)?; // description
-    let (_, tail) = take_bytes(tail, 12)?;
-    let (_, tail) = take_string(tail)?; // radar name
-    let (_, tail) = take_bytes(tail, 12)?;
-    let (capture_time, tail) = take_u32(tail)?; // volume scan start time
-    let (_, tail) = take_bytes(tail, 8)?;
-    let (scan_number, tail) = take_i32(tail)?;
-    check_range_inclusive(
-        ProductSymbology::SCAN_NUMBER_RANGE,
-        scan_number,
-        "scan number",
-        ProductSymbology::NAME,
-    )?;
-    let (_, tail) = take_bytes(tail, 24)?;
-    let (number_of_components, tail) = take_i32(tail)?;
-    if number_of_components != 1 {
-        return Err(DiprError::Unsupported(format!(
-            "found number of components in product symbology not equal to 1 (got {}); DIPR files containing multiple components are not supported",
-            number_of_components
-        )));
-    }
-    let (_, tail) = take_bytes(tail, (number_of_components * 8) as u16)?;
-
-    // Radial Component Data Structure (Figure E-3)
-    let (radial_component_type, tail) = take_i32(tail)?;
-    check_value(
-        ProductSymbology::RADIAL_COMPONENT_TYPE_VALUE,
-        radial_component_type,
-        "radial component type",
-        ProductSymbology::NAME,
-    )?;
-    let (_, tail) = take_string(tail)?; // description
-    let (bin_size, tail) = take_float(tail)?;
-    check_range_inclusive(
-        ProductSymbology::BIN_SIZE_RANGE,
-        bin_size,
-        "bin size",
-        ProductSymbology::NAME,
-    )?;
-    let (range_to_first_bin, tail) = take_float(tail)?;
-    let (_, tail) = take_bytes(tail, 8)?;
-    let (num_radials, mut tail) = take_i32(tail)?;
-    check_range_inclusive(
-        ProductSymbology::NUM_RADIALS_RANGE,
-        num_radials,
-        "num radials",
-        ProductSymbology::NAME,
-    )?;
+/// Parse Product Symbology of a product of type `product_type`
+pub(crate) fn product_symbology<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+    product_type: ProductType,
+) -> ParseResult<'a, ProductSymbology> {
+    let ((description_data, number_of_components), mut tail) = symbology_header(input, validator)?;
 
-    // parse the radials themselves
-    let mut radials: Vec<Radial> = Vec::with_capacity(num_radials as usize);
-    for _ in 0..num_radials {
-        let tmp = radial(tail)?;
-        radials.push(tmp.0);
-        tail = tmp.1;
+    let mut primary = None;
+    let mut radial_component_index = 0;
+    let mut components = vec![];
+    for _ in 0..number_of_components {
+        let (_, t) = take_component_pointer(tail)?;
+        let (component, t) = component(t, validator, product_type)?;
+        tail = t;
+        match component {
+            Component::Radial(radial_component) if primary.is_none() => {
+                radial_component_index = components.len();
+                primary = Some(radial_component)
+            }
+            c => components.push(c),
+        }
     }
-
-    let range_to_first_bin = Length::new::<meter>(range_to_first_bin);
-    let bin_size = Length::new::<meter>(bin_size);
-    let scan_number = scan_number as u8;
-    let capture_time = match DateTime::from_timestamp(capture_time as i64, 0) {
-        Some(t) => t,
-        None => return Err(DiprError::InvalidCaptureTime(capture_time)),
+    let Some(mut radial_component) = primary else {
+        return Err(DiprError::Unsupported(
+            "found no radial component in product symbology".to_string(),
+        ));
     };
 
+    let bin_size = radial_component.bin_size;
+    let range_to_first_bin = radial_component.range_to_first_bin;
+    let radials = std::mem::take(&mut radial_component.radials);
+    let capture_time = description_data.volume_scan_start_time;
+
     Ok((
         ProductSymbology {
             range_to_first_bin,
             bin_size,
-            scan_number,
             capture_time,
             radials,
+            radial_component,
+            radial_component_index,
+            components,
+            description_data,
+        },
+        tail,
+    ))
+}
+
+/// Parse Product Symbology of a digital radial product of type `product_type`
+///
+/// These products have no Product Description Data Structure or components, so their metadata is
+/// filled in from `product_description` and `radar_name`. The block holds a single layer with a
+/// single packet.
+pub(crate) fn digital_product_symbology<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+    product_type: ProductType,
+    product_description: &ProductDescription,
+    radar_name: &str,
+) -> ParseResult<'a, ProductSymbology> {
+    let thresholds = product_description.thresholds().ok_or_else(|| {
+        DiprError::Unsupported(format!("{product_type} isn't a digital radial product"))
+    })?;
+    // only the base products have a single elevation, which they store in place of the
+    // precipitation flag
+    let elevation = match product_type {
+        ProductType::DigitalReflectivity => product_description.product_dependent[2] as f32 / 10.,
+        _ => 0.,
+    };
+    let elevation = Angle::new::<degree>(elevation);
+
+    let (_, tail) = take_bytes(input, ProductSymbology::HEADER_LENGTH)?;
+    let (digital, tail) = digital_radials(tail, validator, product_type, &thresholds, elevation)?;
+
+    let description_data = ProductDescriptionData::from_product_description(
+        product_description,
+        product_type,
+        radar_name,
+        elevation,
+    );
+    Ok((
+        ProductSymbology {
+            range_to_first_bin: digital.range_to_first_bin,
+            bin_size: digital.bin_size,
+            capture_time: product_description.volume_scan_time,
+            radials: digital.radials,
+            radial_component: RadialComponent {
+                description: String::new(),
+                bin_size: digital.bin_size,
+                range_to_first_bin: digital.range_to_first_bin,
+                parameters: Default::default(),
+                radials: vec![],
+            },
+            radial_component_index: 0,
+            components: vec![],
+            description_data,
         },
         tail,
     ))
 }
+
+/// Parse everything in the product symbology block up to the first component
+///
+/// This returns the Product Description Data Structure along with the number of components that
+/// follow.
+pub(crate) fn symbology_header<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+) -> ParseResult<'a, (ProductDescriptionData, usize)> {
+    // header (Figure 3-6, Sheet 7)
+    let (_, tail) = take_bytes(input, ProductSymbology::HEADER_LENGTH)?;
+
+    // another header (Figure 3-15c)
+    let (_, tail) = take_bytes(tail, ProductSymbology::PACKET_HEADER_LENGTH)?;
+
+    // Product Description Data Structure header (Figure E-1)
+    let (description_data, tail) = product_description_data(tail, validator)?;
+
+    // Components are stored as an XDR array of pointers, so the number of components is
+    // repeated as the array length, and each component is preceded by a non-null pointer marker
+    let (number_of_components, tail) = take_i32(tail)?;
+    validator.check_range_inclusive(
+        ProductSymbology::NUM_COMPONENTS_RANGE,
+        number_of_components,
+        "number of components",
+        ProductSymbology::NAME,
+        tail,
+    )?;
+    let (_array_length, tail) = take_i32(tail)?;
+
+    Ok((
+        (description_data, number_of_components.max(0) as usize),
+        tail,
+    ))
+}
+
+/// Consume the pointer marker that precedes each component
+pub(crate) fn take_component_pointer(input: &[u8]) -> ParseResult<'_, ()> {
+    let (_, tail) = take_i32(input)?;
+    Ok(((), tail))
+}
+
+/// Encode the product symbology block of a product of type `product_type` with
+/// `radial_component` inserted into `components` at `radial_component_index`
+///
+/// The radials are taken from `radials` instead of [`RadialComponent::radials`]. The lengths in
+/// the block and packet headers are computed from the encoded data. Fails if
+/// `radial_component_index` is past the end of `components`.
+pub(crate) fn encode_product_symbology(
+    product_type: ProductType,
+    description_data: &ProductDescriptionData,
+    radial_component: &RadialComponent,
+    radials: &[Radial],
+    radial_component_index: usize,
+    components: &[Component],
+    out: &mut Vec<u8>,
+) -> Result<(), DiprError> {
+    let (before, after) = components
+        .split_at_checked(radial_component_index)
+        .ok_or_else(|| {
+            DiprError::Unencodable(format!(
+                "radial component index {radial_component_index} is past the {} other components",
+                components.len()
+            ))
+        })?;
+
+    let mut data = vec![];
+    encode_product_description_data(description_data, &mut data)?;
+    let num_components = components.len() as i32 + 1;
+    put_i32(&mut data, num_components);
+    put_i32(&mut data, num_components);
+    for component in before {
+        put_i32(&mut data, Component::POINTER_MARKER);
+        encode_component(component, product_type, &mut data)?;
+    }
+    put_i32(&mut data, Component::POINTER_MARKER);
+    put_i32(&mut data, Component::RADIAL_TYPE);
+    encode_radial_component(radial_component, radials, product_type, &mut data)?;
+    for component in after {
+        put_i32(&mut data, Component::POINTER_MARKER);
+        encode_component(component, product_type, &mut data)?;
+    }
+
+    let layer_length = ProductSymbology::PACKET_HEADER_LENGTH + data.len();
+    let block_length = ProductSymbology::HEADER_LENGTH + layer_length;
+    put_i16(out, ProductSymbology::BLOCK_DIVIDER);
+    put_i16(out, ProductSymbology::BLOCK_ID);
+    put_u32(out, block_length as u32);
+    put_i16(out, ProductSymbology::NUM_LAYERS);
+    put_i16(out, ProductSymbology::LAYER_DIVIDER);
+    put_u32(out, layer_length as u32);
+    put_i16(out, ProductSymbology::GENERIC_PACKET_CODE);
+    put_i16(out, 0); // reserved
+    put_u32(out, data.len() as u32);
+    out.extend_from_slice(&data);
+    Ok(())
+}
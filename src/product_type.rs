@@ -0,0 +1,293 @@
+use std::fmt::Display;
+
+use uom::si::{
+    areal_mass_density::kilogram_per_square_meter,
+    f32::{ArealMassDensity, Length, Velocity},
+    length::inch,
+};
+
+use crate::{DiprError, inch_per_hour};
+
+/// Level III product whose radials this crate can decode
+///
+/// DIPR uses the generic radial format, where every bin is a 32-bit value whose low 16 bits hold a
+/// data level. The rest are digital radial products (see [`ProductType::is_digital_radial`]),
+/// where every bin is an 8-bit data level that the [`Thresholds`](crate::Thresholds) in the
+/// product description block map to a physical value. The product code in the product description
+/// block decides which variant applies.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ProductType {
+    /// Digital Hybrid Scan Reflectivity (DHR, product code 32)
+    DigitalHybridReflectivity,
+    /// Digital Base Reflectivity (DR, product code 94)
+    DigitalReflectivity,
+    /// Digital Vertically Integrated Liquid (DVL, product code 134)
+    DigitalVil,
+    /// Digital Accumulation Array (DAA, product code 170)
+    DigitalAccumulationArray,
+    /// Digital Storm Total Accumulation (DSA, product code 172)
+    DigitalStormTotalAccumulation,
+    /// Digital User-Selectable Accumulation (DUA, product code 173)
+    DigitalUserSelectableAccumulation,
+    /// Digital Instantaneous Precipitation Rate (DPR, product code 176)
+    PrecipRate,
+}
+
+/// Physical quantity that the bins of a product hold
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Quantity {
+    /// Precipitation rate, given in in/hr
+    PrecipRate,
+    /// Depth of precipitation accumulated over some period, given in inches
+    Accumulation,
+    /// Radar reflectivity factor, given in dBZ
+    Reflectivity,
+    /// Vertically integrated liquid water content, given in kg/m²
+    Vil,
+}
+
+/// Physical value of one bin
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub enum Measurement {
+    /// Precipitation rate, for products whose [`Quantity`] is [`Quantity::PrecipRate`]
+    Rate(Velocity),
+    /// Accumulated depth, for products whose [`Quantity`] is [`Quantity::Accumulation`]
+    Depth(Length),
+    /// Reflectivity in dBZ, for products whose [`Quantity`] is [`Quantity::Reflectivity`]
+    ///
+    /// This is a bare number because reflectivity is logarithmic, which [`uom`] can't express.
+    Reflectivity(f32),
+    /// Liquid water content of a column, for products whose [`Quantity`] is [`Quantity::Vil`]
+    Vil(ArealMassDensity),
+}
+
+impl ProductType {
+    /// Every supported product in order of product code
+    pub const ALL: [ProductType; 7] = [
+        ProductType::DigitalHybridReflectivity,
+        ProductType::DigitalReflectivity,
+        ProductType::DigitalVil,
+        ProductType::DigitalAccumulationArray,
+        ProductType::DigitalStormTotalAccumulation,
+        ProductType::DigitalUserSelectableAccumulation,
+        ProductType::PrecipRate,
+    ];
+
+    /// Product for `code`, or [`None`] if it isn't supported
+    pub fn from_code(code: i16) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.code() == code)
+    }
+
+    /// Product code as it appears in the product description block
+    pub fn code(&self) -> i16 {
+        match self {
+            ProductType::DigitalHybridReflectivity => 32,
+            ProductType::DigitalReflectivity => 94,
+            ProductType::DigitalVil => 134,
+            ProductType::DigitalAccumulationArray => 170,
+            ProductType::DigitalStormTotalAccumulation => 172,
+            ProductType::DigitalUserSelectableAccumulation => 173,
+            ProductType::PrecipRate => 176,
+        }
+    }
+
+    /// Short abbreviation of the product, e.g., `DPR`
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            ProductType::DigitalHybridReflectivity => "DHR",
+            ProductType::DigitalReflectivity => "DR",
+            ProductType::DigitalVil => "DVL",
+            ProductType::DigitalAccumulationArray => "DAA",
+            ProductType::DigitalStormTotalAccumulation => "DSA",
+            ProductType::DigitalUserSelectableAccumulation => "DUA",
+            ProductType::PrecipRate => "DPR",
+        }
+    }
+
+    /// Physical quantity that the bins of this product hold
+    pub fn quantity(&self) -> Quantity {
+        match self {
+            ProductType::DigitalHybridReflectivity | ProductType::DigitalReflectivity => {
+                Quantity::Reflectivity
+            }
+            ProductType::DigitalVil => Quantity::Vil,
+            ProductType::PrecipRate => Quantity::PrecipRate,
+            _ => Quantity::Accumulation,
+        }
+    }
+
+    /// Whether this product stores its radials in the digital radial data array packet (packet
+    /// code 16) rather than the generic radial format
+    ///
+    /// Only [`parse_dipr`](crate::parse_dipr) and friends can read these products, and they
+    /// can't be encoded.
+    pub fn is_digital_radial(&self) -> bool {
+        matches!(
+            self,
+            ProductType::DigitalHybridReflectivity
+                | ProductType::DigitalReflectivity
+                | ProductType::DigitalVil
+                | ProductType::DigitalAccumulationArray
+                | ProductType::DigitalStormTotalAccumulation
+                | ProductType::DigitalUserSelectableAccumulation
+        )
+    }
+
+    /// Physical value of one data level, in the unit given by [`Quantity::unit`]
+    ///
+    /// DIPR gives rates in thousandths of an in/hr (Table V). Digital radial products have no fixed
+    /// scale because their [`Thresholds`](crate::Thresholds) map data levels to values, so this is
+    /// 1 for them.
+    pub fn scale(&self) -> f32 {
+        match self {
+            ProductType::PrecipRate => 0.001,
+            _ => 1.,
+        }
+    }
+
+    /// Convert a data level into the physical value that it stands for
+    ///
+    /// For digital radial products, this gives the data level itself. Their radials are decoded
+    /// with [`Thresholds::decode`](crate::Thresholds::decode) instead.
+    pub fn decode(&self, level: u16) -> Measurement {
+        self.quantity().measurement(level as f32 * self.scale())
+    }
+
+    /// Convert a physical value into the nearest data level, saturating at the largest one
+    ///
+    /// This fails if `measurement` is a different quantity than this product holds or if this is
+    /// a digital radial product.
+    pub fn encode(&self, measurement: Measurement) -> Result<u16, DiprError> {
+        if self.is_digital_radial() {
+            return Err(DiprError::Unencodable(format!(
+                "{} is a digital radial product, which can't be encoded",
+                self.mnemonic()
+            )));
+        }
+        if measurement.quantity() != self.quantity() {
+            return Err(DiprError::Unencodable(format!(
+                "{} product can't hold a value of {measurement}",
+                self.mnemonic()
+            )));
+        }
+        Ok(self.level(measurement.value()))
+    }
+
+    /// Nearest data level to `value`, which is given in the unit of [`Quantity::unit`]
+    pub(crate) fn level(&self, value: f32) -> u16 {
+        (value / self.scale()).round().clamp(0., u16::MAX as f32) as u16
+    }
+}
+
+impl Display for ProductType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.mnemonic(), self.code())
+    }
+}
+
+impl Quantity {
+    /// Abbreviation of the unit that values of this quantity are given in
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Quantity::PrecipRate => "in/hr",
+            Quantity::Accumulation => "in",
+            Quantity::Reflectivity => "dBZ",
+            Quantity::Vil => "kg/m²",
+        }
+    }
+
+    /// Name of the property that holds values of this quantity in GeoJSON output
+    pub fn property_name(&self) -> &'static str {
+        match self {
+            Quantity::PrecipRate => "precipRate",
+            Quantity::Accumulation => "accumulation",
+            Quantity::Reflectivity => "reflectivity",
+            Quantity::Vil => "vil",
+        }
+    }
+
+    /// Measurement of this quantity whose value in [`Quantity::unit`] is `value`
+    pub fn measurement(&self, value: f32) -> Measurement {
+        match self {
+            Quantity::PrecipRate => Measurement::Rate(Velocity::new::<inch_per_hour>(value)),
+            Quantity::Accumulation => Measurement::Depth(Length::new::<inch>(value)),
+            Quantity::Reflectivity => Measurement::Reflectivity(value),
+            Quantity::Vil => {
+                Measurement::Vil(ArealMassDensity::new::<kilogram_per_square_meter>(value))
+            }
+        }
+    }
+}
+
+impl Measurement {
+    /// Physical quantity of this measurement
+    pub fn quantity(&self) -> Quantity {
+        match self {
+            Measurement::Rate(_) => Quantity::PrecipRate,
+            Measurement::Depth(_) => Quantity::Accumulation,
+            Measurement::Reflectivity(_) => Quantity::Reflectivity,
+            Measurement::Vil(_) => Quantity::Vil,
+        }
+    }
+
+    /// Value of this measurement in the unit given by [`Quantity::unit`]
+    pub fn value(&self) -> f32 {
+        match self {
+            Measurement::Rate(r) => r.get::<inch_per_hour>(),
+            Measurement::Depth(d) => d.get::<inch>(),
+            Measurement::Reflectivity(r) => *r,
+            Measurement::Vil(v) => v.get::<kilogram_per_square_meter>(),
+        }
+    }
+
+    /// Whether this is a zero rate, accumulation, or VIL, which means that no precipitation was
+    /// found
+    ///
+    /// Reflectivity is logarithmic, so 0 dBZ is a weak echo rather than the absence of one, and
+    /// this is always false for it.
+    pub fn is_no_precipitation(&self) -> bool {
+        match self {
+            Measurement::Reflectivity(_) => false,
+            m => m.value() == 0.,
+        }
+    }
+
+    /// Precipitation rate, or [`None`] if this is a different quantity
+    pub fn rate(&self) -> Option<Velocity> {
+        match self {
+            Measurement::Rate(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    /// Accumulated depth, or [`None`] if this is a different quantity
+    pub fn depth(&self) -> Option<Length> {
+        match self {
+            Measurement::Depth(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    /// Reflectivity in dBZ, or [`None`] if this is a different quantity
+    pub fn reflectivity(&self) -> Option<f32> {
+        match self {
+            Measurement::Reflectivity(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    /// Vertically integrated liquid, or [`None`] if this is a different quantity
+    pub fn vil(&self) -> Option<ArealMassDensity> {
+        match self {
+            Measurement::Vil(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Measurement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.3} {}", self.value(), self.quantity().unit())
+    }
+}
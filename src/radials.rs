@@ -1,14 +1,14 @@
-use std::ops::RangeInclusive;
+use std::{collections::BTreeMap, ops::RangeInclusive};
 
-use uom::si::{
-    angle::degree,
-    f32::{Angle, Velocity},
-};
+use uom::si::{angle::degree, f32::Angle};
 
-use crate::{ParseResult, inch_per_hour, utils::*};
+use crate::{
+    DiprError, Measurement, ParseResult, ProductType, Thresholds, parameters::parse_attributes,
+    utils::*,
+};
 
 #[derive(Clone, Debug, PartialEq, PartialOrd, Default)]
-/// Precipitation rates measured in a particular direction
+/// Values measured in a particular direction, e.g., precipitation rates
 pub struct Radial {
     /// Bearing along which this radial points
     pub azimuth: Angle,
@@ -16,56 +16,413 @@ pub struct Radial {
     pub elevation: Angle,
     /// Angular size of this radial
     pub width: Angle,
-    /// Measured precipitation rates in each bin in this radial in ascending order of distance
-    pub precip_rates: Vec<Velocity>,
+    /// Measured value of each bin in this radial in ascending order of distance
+    ///
+    /// These come from the low 16 bits of each bin's raw value as scaled by the product's
+    /// [`ProductType`], or from the data level as converted by the product's [`Thresholds`] for
+    /// digital radial products. Bins that are flagged as holding no data still get a value here.
+    /// Use [`Radial::bin_values`] to tell them apart.
+    pub values: Vec<Measurement>,
+    /// Raw 32-bit value of each bin in this radial, in the same order as `values`
+    ///
+    /// For digital radial products, this is the 8-bit data level of each bin.
+    pub raw_values: Vec<u32>,
+    /// Per-radial attribute string exactly as it appeared in the product
+    ///
+    /// This is often empty.
+    pub raw_attributes: String,
+    /// Attributes parsed into key/value pairs
+    ///
+    /// Structured attribute strings look like `key=value;key=value;`. A segment without an equals
+    /// sign is kept as a key with an empty value.
+    pub attributes: BTreeMap<String, String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+/// Meaning of a bin's raw value
+///
+/// Only the reflectivity and VIL products reserve data levels for bins that don't hold a value.
+/// The generic radial format has no such codes: Table V lists no data level thresholds for DIPR,
+/// and the Radial Information Data Structure (Figure E-4) stores its bins as plain integers. Level
+/// 0 of a digital accumulation product means that nothing accumulated. Every bin of those products
+/// holds a value.
+pub enum BinValue {
+    /// Measured value, which is zero when no precipitation was detected
+    Value(Measurement),
+    /// No data is available for this bin, e.g., because it's range folded or outside the scan
+    NoData,
+    /// Value was below the product's lowest threshold, e.g., because the signal was too weak to
+    /// measure
+    BelowThreshold,
+}
+
+impl BinValue {
+    /// Data level that marks a bin below the threshold in a digital radial product
+    const BELOW_THRESHOLD_LEVEL: u32 = 0;
+
+    /// Decode a raw bin value from a product of type `product_type`
+    ///
+    /// For generic radial products, the low 16 bits hold a data level that
+    /// [`ProductType::decode`] converts into a physical value, just like [`Radial::values`]. The
+    /// raw value of a digital radial product is its 8-bit data level, where the levels below
+    /// [`Thresholds::first_data_level`] are flags. Other levels can only be converted with the
+    /// product's [`Thresholds`], so the value is the data level itself. [`Radial::bin_values`]
+    /// gives the converted values instead.
+    pub fn from_raw(raw: u32, product_type: ProductType) -> Self {
+        if !product_type.is_digital_radial() {
+            return BinValue::Value(product_type.decode(raw as u16));
+        }
+        match raw {
+            r if r > u8::MAX as u32 => BinValue::NoData,
+            r if r >= Thresholds::first_data_level(product_type) as u32 => {
+                BinValue::Value(product_type.decode(raw as u16))
+            }
+            Self::BELOW_THRESHOLD_LEVEL => BinValue::BelowThreshold,
+            _ => BinValue::NoData,
+        }
+    }
+
+    /// Measured value of this bin, or [`None`] if it doesn't hold one
+    pub fn value(&self) -> Option<Measurement> {
+        match self {
+            BinValue::Value(v) => Some(*v),
+            _ => None,
+        }
+    }
 }
 
 impl Radial {
-    const NAME: &'static str = "radial";
+    pub(crate) const NAME: &'static str = "radial";
     const AZIMUTH_RANGE: RangeInclusive<f32> = (0.)..=360.;
     const ELEVATION_RANGE: RangeInclusive<f32> = (-1.)..=45.;
-    const WIDTH_RANGE: RangeInclusive<f32> = (0.)..=2.;
-    const NUM_BINS_RANGE: RangeInclusive<i32> = 0..=1840;
+    pub(crate) const WIDTH_RANGE: RangeInclusive<f32> = (0.)..=2.;
+    pub(crate) const NUM_BINS_RANGE: RangeInclusive<i32> = 0..=1840;
+}
+
+impl Radial {
+    /// Decode the raw value of each bin in this radial in ascending order of distance, given that
+    /// it came from a product of type `product_type`
+    ///
+    /// Bins that hold a value are given the one in [`Radial::values`].
+    pub fn bin_values(&self, product_type: ProductType) -> impl Iterator<Item = BinValue> + '_ {
+        self.raw_values
+            .iter()
+            .zip(&self.values)
+            .map(
+                move |(raw, value)| match BinValue::from_raw(*raw, product_type) {
+                    BinValue::Value(_) => BinValue::Value(*value),
+                    bin_value => bin_value,
+                },
+            )
+    }
+
+    /// Whether the bin at `bin_idx` holds a measured value, given that this radial came from a
+    /// product of type `product_type`
+    ///
+    /// This is also true if there is no raw value for the bin, e.g., because the radial was built
+    /// by hand with only `values`.
+    pub fn has_value(&self, bin_idx: usize, product_type: ProductType) -> bool {
+        self.raw_values
+            .get(bin_idx)
+            .is_none_or(|raw| matches!(BinValue::from_raw(*raw, product_type), BinValue::Value(_)))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+/// Borrowed view of a radial that decodes bins on demand
+///
+/// Unlike [`Radial`], this doesn't allocate. The bins stay in the decompressed payload until they
+/// are read.
+pub struct RadialView<'a> {
+    /// Bearing along which this radial points
+    pub azimuth: Angle,
+    /// Angle that the radar beam made with respect to horizontal
+    pub elevation: Angle,
+    /// Angular size of this radial
+    pub width: Angle,
+    /// Per-radial attribute string exactly as it appeared in the product
+    pub raw_attributes: &'a str,
+    /// Product whose data levels the bins hold
+    pub product_type: ProductType,
+    bins: &'a [[u8; 4]],
+}
+
+impl<'a> RadialView<'a> {
+    /// Number of bins in this radial
+    pub fn num_bins(&self) -> usize {
+        self.bins.len()
+    }
+
+    /// Raw bins as they appear in the payload, each a big-endian 32-bit value
+    pub fn raw_bins(&self) -> &'a [[u8; 4]] {
+        self.bins
+    }
+
+    /// Raw 32-bit value of the bin at `bin_idx`
+    pub fn raw_value(&self, bin_idx: usize) -> Option<u32> {
+        self.bins.get(bin_idx).map(|bin| u32::from_be_bytes(*bin))
+    }
+
+    /// Decoded value of the bin at `bin_idx`
+    pub fn bin_value(&self, bin_idx: usize) -> Option<BinValue> {
+        self.raw_value(bin_idx)
+            .map(|raw| BinValue::from_raw(raw, self.product_type))
+    }
+
+    /// Measured value of the bin at `bin_idx` as in [`Radial::values`]
+    ///
+    /// This is [`None`] if there's no such bin or if this is a digital radial product (see
+    /// [`ProductType::is_digital_radial`]), whose data levels can only be converted with the
+    /// product's [`Thresholds`].
+    pub fn value(&self, bin_idx: usize) -> Option<Measurement> {
+        if self.product_type.is_digital_radial() {
+            return None;
+        }
+        self.raw_value(bin_idx)
+            .map(|raw| self.product_type.decode(raw as u16))
+    }
+
+    /// Raw value of each bin in ascending order of distance
+    pub fn raw_values(&self) -> impl Iterator<Item = u32> + 'a {
+        self.bins.iter().map(|bin| u32::from_be_bytes(*bin))
+    }
+
+    /// Measured value of each bin in ascending order of distance as in [`Radial::values`], or
+    /// [`None`] if this is a digital radial product as in [`RadialView::value`]
+    pub fn values(&self) -> Option<impl Iterator<Item = Measurement> + 'a> {
+        let product_type = self.product_type;
+        if product_type.is_digital_radial() {
+            return None;
+        }
+        Some(
+            self.raw_values()
+                .map(move |raw| product_type.decode(raw as u16)),
+        )
+    }
+
+    /// Decode every bin into an owned [`Radial`], or [`None`] if this is a digital radial product
+    /// as in [`RadialView::value`]
+    pub fn to_radial(self) -> Option<Radial> {
+        Some(Radial {
+            azimuth: self.azimuth,
+            elevation: self.elevation,
+            width: self.width,
+            values: self.values()?.collect(),
+            raw_values: self.raw_values().collect(),
+            raw_attributes: self.raw_attributes.to_string(),
+            attributes: parse_attributes(self.raw_attributes),
+        })
+    }
 }
 
-/// Parse Radial Information Data Structure (Figure E-4)
-pub(crate) fn radial(input: &[u8]) -> ParseResult<Radial> {
+/// Parse Radial Information Data Structure (Figure E-4) from a product of type `product_type`
+pub(crate) fn radial<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+    product_type: ProductType,
+) -> ParseResult<'a, Radial> {
+    let (view, tail) = radial_view(input, validator, product_type)?;
+    let radial = view.to_radial().ok_or_else(|| {
+        DiprError::Unsupported(format!(
+            "{product_type} is a digital radial product, which doesn't use the generic radial format"
+        ))
+    })?;
+    Ok((radial, tail))
+}
+
+/// Parse Radial Information Data Structure (Figure E-4) without decoding the bins
+pub(crate) fn radial_view<'a>(
+    input: &'a [u8],
+    validator: &mut Validator,
+    product_type: ProductType,
+) -> ParseResult<'a, RadialView<'a>> {
     let (azimuth, tail) = take_float(input)?;
-    check_range_inclusive(Radial::AZIMUTH_RANGE, azimuth, "azimuth", Radial::NAME)?;
+    validator.check_range_inclusive(
+        Radial::AZIMUTH_RANGE,
+        azimuth,
+        "azimuth",
+        Radial::NAME,
+        tail,
+    )?;
 
     let (elevation, tail) = take_float(tail)?;
-    check_range_inclusive(
+    validator.check_range_inclusive(
         Radial::ELEVATION_RANGE,
         elevation,
         "elevation",
         Radial::NAME,
+        tail,
     )?;
 
     let (width, tail) = take_float(tail)?;
-    check_range_inclusive(Radial::WIDTH_RANGE, width, "width", Radial::NAME)?;
+    validator.check_range_inclusive(Radial::WIDTH_RANGE, width, "width", Radial::NAME, tail)?;
 
     let (num_bins, tail) = take_i32(tail)?;
-    check_range_inclusive(Radial::NUM_BINS_RANGE, num_bins, "num bins", Radial::NAME)?;
-
-    let (_attributes, tail) = take_string(tail)?;
-    let (_, tail) = take_bytes(tail, 4)?;
-    let mut precip_rates = Vec::with_capacity(num_bins as usize);
-    let (precip_rate_bytes, tail) = take_bytes(tail, (num_bins * 4) as u16)?;
-    for idx in 0..num_bins {
-        let buf: [u8; 2] = precip_rate_bytes[(idx * 4 + 2) as usize..(idx * 4 + 4) as usize]
-            .try_into()
-            .unwrap();
-        precip_rates.push(Velocity::new::<inch_per_hour>(
-            u16::from_be_bytes(buf) as f32 / 1000.,
-        ));
-    }
+    validator.check_range_inclusive(
+        Radial::NUM_BINS_RANGE,
+        num_bins,
+        "num bins",
+        Radial::NAME,
+        tail,
+    )?;
+    let num_bins = validator.check_count(
+        validator.options().max_bins,
+        num_bins,
+        "num bins",
+        Radial::NAME,
+        tail,
+    )?;
+
+    let (raw_attributes, tail) = take_str(tail)?;
+
+    // the bins are stored as an XDR array, so the number of bins is repeated as its length
+    let (array_length, tail) = take_i32(tail)?;
+    check_value(
+        num_bins as i32,
+        array_length,
+        "bin array length",
+        Radial::NAME,
+        tail,
+    )?;
+
+    let (bin_bytes, tail) = take_bytes(tail, num_bins * 4).map_err(|e| {
+        e.with_context(|c| {
+            c.section = Radial::NAME;
+            c.field = Some("bins");
+            c.bin = Some(tail.len() / 4);
+            c.remaining = Some(tail.len() % 4);
+        })
+    })?;
+    let (bins, _) = bin_bytes.as_chunks::<4>();
+
     Ok((
-        Radial {
+        RadialView {
             azimuth: Angle::new::<degree>(azimuth),
             elevation: Angle::new::<degree>(elevation),
             width: Angle::new::<degree>(width),
-            precip_rates,
+            raw_attributes,
+            product_type,
+            bins,
         },
         tail,
     ))
 }
+
+/// Encode Radial Information Data Structure (Figure E-4) for a product of type `product_type`
+///
+/// Each bin is written from its raw value if [`Radial::raw_values`] has one that agrees with
+/// [`Radial::values`], so flags survive unless the value was changed. Otherwise, the value is
+/// rounded to the nearest data level with [`ProductType::encode`]. [`Radial::raw_attributes`] is
+/// written as-is.
+pub(crate) fn encode_radial(
+    radial: &Radial,
+    product_type: ProductType,
+    out: &mut Vec<u8>,
+) -> Result<(), DiprError> {
+    put_float(out, radial.azimuth.get::<degree>());
+    put_float(out, radial.elevation.get::<degree>());
+    put_float(out, radial.width.get::<degree>());
+    put_i32(out, radial.values.len() as i32);
+    put_string(out, &radial.raw_attributes);
+    put_i32(out, radial.values.len() as i32);
+    for (bin_idx, value) in radial.values.iter().enumerate() {
+        let raw = match radial.raw_values.get(bin_idx) {
+            Some(&raw) if product_type.decode(raw as u16) == *value => raw,
+            _ => product_type.encode(*value)?.into(),
+        };
+        put_u32(out, raw);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    /// Radial at 90 degrees with the given attributes and bins, whose array length is
+    /// `array_length`
+    fn radial_bytes(attributes: &str, bins: &[u32], array_length: i32) -> Vec<u8> {
+        let mut out = vec![];
+        put_float(&mut out, 90.);
+        put_float(&mut out, 0.5);
+        put_float(&mut out, 1.);
+        put_i32(&mut out, bins.len() as i32);
+        put_string(&mut out, attributes);
+        put_i32(&mut out, array_length);
+        for bin in bins {
+            put_u32(&mut out, *bin);
+        }
+        out
+    }
+
+    fn parse(input: &[u8]) -> Result<Radial, DiprError> {
+        let mut validator = Validator::new(&ParseOptions::default());
+        let (radial, tail) = radial(input, &mut validator, ProductType::PrecipRate)?;
+        assert!(tail.is_empty());
+        Ok(radial)
+    }
+
+    #[test]
+    fn attributes_are_kept_and_parsed() {
+        let input = radial_bytes("quality=good;clutter;", &[0, 1000], 2);
+        let radial = parse(&input).unwrap();
+        assert_eq!(radial.azimuth, Angle::new::<degree>(90.));
+        assert_eq!(radial.raw_attributes, "quality=good;clutter;");
+        assert_eq!(radial.attributes["quality"], "good");
+        assert_eq!(radial.attributes["clutter"], "");
+        assert_eq!(radial.raw_values, [0, 1000]);
+
+        let mut out = vec![];
+        encode_radial(&radial, ProductType::PrecipRate, &mut out).unwrap();
+        assert_eq!(out, input);
+    }
+
+    #[test]
+    fn views_of_digital_products_have_no_values() {
+        let input = radial_bytes("", &[0, 1, 2, 200], 4);
+        let mut validator = Validator::new(&ParseOptions::default());
+        let (mut view, _) = radial_view(&input, &mut validator, ProductType::PrecipRate).unwrap();
+        assert_eq!(view.value(3), Some(ProductType::PrecipRate.decode(200)));
+        assert_eq!(view.values().unwrap().count(), 4);
+
+        view.product_type = ProductType::DigitalReflectivity;
+        assert_eq!(view.value(3), None);
+        assert!(view.values().is_none());
+        assert_eq!(view.to_radial(), None);
+        assert_eq!(view.raw_value(3), Some(200));
+        assert_eq!(view.bin_value(0), Some(BinValue::BelowThreshold));
+        assert_eq!(view.bin_value(1), Some(BinValue::NoData));
+
+        let e = radial(&input, &mut validator, ProductType::DigitalReflectivity).unwrap_err();
+        assert!(
+            matches!(e.without_context(), DiprError::Unsupported(_)),
+            "{e:?}"
+        );
+    }
+
+    #[test]
+    fn bin_array_length_must_match_num_bins() {
+        let input = radial_bytes("", &[0, 1000, 2000], 2);
+        let e = parse(&input).unwrap_err();
+        let context = e.context().unwrap();
+        assert_eq!(context.field, Some("bin array length"));
+        // the array length is followed only by the bins
+        assert_eq!(context.remaining, Some(4 + 3 * 4));
+        assert!(
+            matches!(e.without_context(), DiprError::ValueOutOfRange(_)),
+            "{e:?}"
+        );
+    }
+
+    #[test]
+    fn truncated_bins_point_at_the_first_missing_bin() {
+        let mut input = radial_bytes("", &[0, 1000, 2000], 3);
+        input.truncate(input.len() - 6);
+        let e = parse(&input).unwrap_err();
+        let context = e.context().unwrap();
+        assert_eq!(context.field, Some("bins"));
+        assert_eq!(context.bin, Some(1));
+        assert_eq!(context.remaining, Some(2));
+    }
+}
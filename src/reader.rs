@@ -0,0 +1,453 @@
+use std::io::{self, Chain, Cursor, Read, Take};
+
+use bzip2::read::BzDecoder;
+use uom::si::f32::Length;
+
+use crate::{
+    Component, DiprError, HEADERS_LENGTH, MessageHeader, ParseOptions, ParseResult, ParseWarning,
+    ProductDescription, ProductDescriptionData, ProductType, Radial, RadialComponent, Stream,
+    TextHeader, Wrapper,
+    components::{component, radial_component_header},
+    generic_product_type, product_headers,
+    product_symbology::{ProductSymbology, symbology_header, take_component_pointer},
+    radials::radial,
+    utils::*,
+    wrapper::{SNIFF_LENGTH, is_uncompressed},
+};
+
+/// Streaming DIPR parser that yields one [`Radial`] at a time
+///
+/// [`parse_dipr`](crate::parse_dipr) needs the whole product in memory and decodes every radial
+/// before returning. This type instead reads the headers eagerly, then decompresses the product
+/// symbology block incrementally as the radials are consumed, so memory use stays bounded no
+/// matter where the input comes from. The same validation as in [`parse_dipr_with`] applies.
+///
+/// Only components up to and including the first radial component are read. Any other components
+/// that precede it are available from [`DiprReader::components`].
+///
+/// An uncompressed product symbology block is detected automatically, but gzip and NOAAPort
+/// containers aren't. Remove those before handing the input to this type, e.g., with
+/// [`flate2::read::MultiGzDecoder`](https://docs.rs/flate2). Digital radial products (see
+/// [`ProductType::is_digital_radial`]) aren't supported.
+///
+/// [`parse_dipr_with`]: crate::parse_dipr_with
+///
+/// ```no_run
+/// use std::{fs::File, io::BufReader};
+///
+/// use dipr::DiprReader;
+///
+/// # fn main() -> Result<(), dipr::DiprError> {
+/// let file = BufReader::new(File::open("sn.last")?);
+/// let mut reader = DiprReader::new(file)?;
+/// println!("{}", reader.text_header().originator);
+/// for radial in &mut reader {
+///     let radial = radial?;
+///     println!("{:?}", radial.azimuth);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct DiprReader<R: Read> {
+    product_type: ProductType,
+    text_header: TextHeader,
+    message_header: MessageHeader,
+    product_description: ProductDescription,
+    description_data: ProductDescriptionData,
+    components: Vec<Component>,
+    radial_component: RadialComponent,
+    num_radials: usize,
+    next_radial: usize,
+    wrappers: Vec<Wrapper>,
+    validator: Validator,
+    stream: DecompressedStream<Chain<Cursor<Vec<u8>>, R>>,
+    done: bool,
+}
+
+impl<R: Read> DiprReader<R> {
+    /// Read the headers of a DIPR product using the default [`ParseOptions`]
+    pub fn new(reader: R) -> Result<Self, DiprError> {
+        Self::with_options(reader, &ParseOptions::default())
+    }
+
+    /// Read the headers of a DIPR product according to `options`
+    ///
+    /// This reads everything up to the first radial, so it fails early if the headers are
+    /// invalid.
+    pub fn with_options(mut reader: R, options: &ParseOptions) -> Result<Self, DiprError> {
+        // if the input is short, the parsers report exactly where it ran out
+        let mut headers = Vec::with_capacity(HEADERS_LENGTH + SNIFF_LENGTH);
+        reader
+            .by_ref()
+            .take((HEADERS_LENGTH + SNIFF_LENGTH) as u64)
+            .read_to_end(&mut headers)?;
+        let mut validator = Validator::new(options);
+        let ((text_header, message_header, product_description), tail) =
+            product_headers(&headers, &mut validator)?;
+        let product_type = generic_product_type(&product_description)?;
+
+        // put back the start of the product symbology block that was read along with the headers
+        let compressed = !is_uncompressed(&product_description, tail);
+        let wrappers = if compressed {
+            vec![]
+        } else {
+            vec![Wrapper::UncompressedSymbology]
+        };
+        let reader = Cursor::new(tail.to_vec()).chain(reader);
+
+        let v = &mut validator;
+        let mut stream = DecompressedStream::new(reader, options.max_uncompressed_size, compressed);
+        let (description_data, number_of_components) =
+            stream.parse_next(ProductSymbology::NAME, None, v, symbology_header)?;
+        let mut components = vec![];
+        for _ in 0..number_of_components {
+            stream.parse_next(ProductSymbology::NAME, None, v, |i, _| {
+                take_component_pointer(i)
+            })?;
+            let component_type =
+                stream.parse_next(ProductSymbology::NAME, None, v, |i, _| peek_i32(i))?;
+            if component_type != Component::RADIAL_TYPE {
+                components.push(stream.parse_next(ProductSymbology::NAME, None, v, |i, v| {
+                    component(i, v, product_type)
+                })?);
+                continue;
+            }
+            stream.parse_next(ProductSymbology::NAME, None, v, |i, _| take_i32(i))?;
+            let (radial_component, num_radials) =
+                stream.parse_next(RadialComponent::NAME, None, v, radial_component_header)?;
+            return Ok(DiprReader {
+                product_type,
+                text_header,
+                message_header,
+                product_description,
+                description_data,
+                components,
+                radial_component,
+                num_radials,
+                next_radial: 0,
+                wrappers,
+                validator,
+                stream,
+                done: false,
+            });
+        }
+        Err(DiprError::Unsupported(
+            "found no radial component in product symbology".to_string(),
+        ))
+    }
+
+    /// Product being read, which decides what the bins hold
+    pub fn product_type(&self) -> ProductType {
+        self.product_type
+    }
+
+    /// WMO text header at the start of the product
+    pub fn text_header(&self) -> &TextHeader {
+        &self.text_header
+    }
+
+    /// Message header that follows the text header
+    pub fn message_header(&self) -> &MessageHeader {
+        &self.message_header
+    }
+
+    /// Every field of the product description block
+    pub fn product_description(&self) -> &ProductDescription {
+        &self.product_description
+    }
+
+    /// Metadata from the start of the product symbology block
+    pub fn description_data(&self) -> &ProductDescriptionData {
+        &self.description_data
+    }
+
+    /// Components that precede the radial component
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// Radial component whose radials this reader yields
+    ///
+    /// [`RadialComponent::radials`] is always empty here.
+    pub fn radial_component(&self) -> &RadialComponent {
+        &self.radial_component
+    }
+
+    /// Distance between the inner and outer extents of each bin measured radially
+    pub fn bin_size(&self) -> Length {
+        self.radial_component.bin_size
+    }
+
+    /// Distance between the radar station and the center of the nearest bin
+    pub fn range_to_first_bin(&self) -> Length {
+        self.radial_component.range_to_first_bin
+    }
+
+    /// Total number of radials in the product, including any that were already read
+    pub fn num_radials(&self) -> usize {
+        self.num_radials
+    }
+
+    /// Containers that were found around the product
+    ///
+    /// This can only ever hold [`Wrapper::UncompressedSymbology`].
+    pub fn wrappers(&self) -> &[Wrapper] {
+        &self.wrappers
+    }
+
+    /// Recoverable problems found so far
+    ///
+    /// The uncompressed size is only checked after the last radial has been read.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        self.validator.warnings()
+    }
+
+    /// Decompress whatever is left after the last radial and check the total size
+    fn finish(&mut self) -> Result<(), DiprError> {
+        let actual = self.stream.skip_to_end()?;
+        if self.wrappers.contains(&Wrapper::UncompressedSymbology) {
+            return Ok(());
+        }
+        self.validator
+            .check_uncompressed_size(self.product_description.uncompressed_size, actual)
+    }
+}
+
+impl<R: Read> Iterator for DiprReader<R> {
+    type Item = Result<Radial, DiprError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.next_radial == self.num_radials {
+            self.done = true;
+            return self.finish().err().map(Err);
+        }
+        let radial_idx = self.next_radial;
+        let product_type = self.product_type;
+        let result = self.stream.parse_next(
+            Radial::NAME,
+            Some(radial_idx),
+            &mut self.validator,
+            |i, v| radial(i, v, product_type),
+        );
+        match result {
+            Ok(radial) => {
+                self.next_radial += 1;
+                Some(Ok(radial))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // there may be one more item if the final size check fails
+        let remaining = self.num_radials - self.next_radial;
+        (0, Some(remaining + 1))
+    }
+}
+
+/// Buffered view of the decompressed product symbology block
+pub(crate) struct DecompressedStream<R: Read> {
+    decoder: Take<Payload<R>>,
+    limit: usize,
+    /// Decompressed bytes that haven't been parsed yet start at `buf[pos]`
+    buf: Vec<u8>,
+    pos: usize,
+    /// Number of decompressed bytes that were dropped from the front of `buf`
+    consumed: usize,
+    eof: bool,
+}
+
+impl<R: Read> DecompressedStream<R> {
+    /// Smallest number of decompressed bytes to request from the decoder at a time
+    const CHUNK_SIZE: usize = 8192;
+
+    /// Read the product symbology block from `reader`, which is bzip2-compressed unless
+    /// `compressed` is false
+    pub(crate) fn new(reader: R, limit: usize, compressed: bool) -> Self {
+        let payload = if compressed {
+            Payload::Bzip2(Box::new(BzDecoder::new(Source {
+                reader,
+                failed: false,
+            })))
+        } else {
+            Payload::Raw(reader)
+        };
+        DecompressedStream {
+            // read one byte past the limit so that we can tell whether it was exceeded
+            decoder: payload.take(limit as u64 + 1),
+            limit,
+            buf: Vec::with_capacity(Self::CHUNK_SIZE),
+            pos: 0,
+            consumed: 0,
+            eof: false,
+        }
+    }
+
+    /// Run `parser` on the buffered input, decompressing more until it has enough
+    ///
+    /// Errors and warnings are located in the decompressed stream and attributed to `section` and
+    /// `radial`.
+    pub(crate) fn parse_next<T>(
+        &mut self,
+        section: &'static str,
+        radial: Option<usize>,
+        validator: &mut Validator,
+        parser: impl for<'a> Fn(&'a [u8], &mut Validator) -> ParseResult<'a, T>,
+    ) -> Result<T, DiprError> {
+        loop {
+            let input = &self.buf[self.pos..];
+            let result = match radial {
+                Some(radial_idx) => validator.in_radial(radial_idx, |v| parser(input, v)),
+                None => parser(input, validator),
+            };
+            match result {
+                Ok((value, tail)) => {
+                    self.pos = self.buf.len() - tail.len();
+                    validator.locate_warnings(
+                        section,
+                        Stream::Decompressed,
+                        self.consumed + self.buf.len(),
+                    );
+                    return Ok(value);
+                }
+                Err(e)
+                    if !self.eof && matches!(e.without_context(), DiprError::Truncated { .. }) =>
+                {
+                    // the parser will see the same values again
+                    validator.discard_pending();
+                    self.fill()?;
+                }
+                Err(e) => {
+                    return Err(e.locate(
+                        section,
+                        Stream::Decompressed,
+                        self.consumed + self.buf.len(),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Drop parsed bytes from the buffer and decompress more onto the end of it
+    ///
+    /// At least as much as is already buffered is added, so a value that spans many chunks is
+    /// parsed a logarithmic number of times rather than once per chunk.
+    fn fill(&mut self) -> Result<(), DiprError> {
+        self.buf.drain(..self.pos);
+        self.consumed += self.pos;
+        self.pos = 0;
+
+        let start = self.buf.len();
+        let wanted = Self::CHUNK_SIZE.max(start);
+        self.buf.resize(start + wanted, 0);
+        let mut filled = 0;
+        while filled < wanted {
+            match self.decoder.read(&mut self.buf[start + filled..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.buf.truncate(start + filled);
+                    return Err(match self.decoder.get_ref() {
+                        Payload::Bzip2(decoder) if !decoder.get_ref().failed => {
+                            DiprError::DecompressionFailed(e)
+                        }
+                        _ => DiprError::Io(e),
+                    });
+                }
+            }
+        }
+        self.buf.truncate(start + filled);
+
+        if self.consumed + self.buf.len() > self.limit {
+            return Err(DiprError::PayloadTooLarge { limit: self.limit });
+        }
+        Ok(())
+    }
+
+    /// Decompress and discard the rest of the stream, returning its total length
+    fn skip_to_end(&mut self) -> Result<usize, DiprError> {
+        while !self.eof {
+            self.pos = self.buf.len();
+            self.fill()?;
+        }
+        Ok(self.consumed + self.buf.len())
+    }
+}
+
+/// Source of the product symbology block
+enum Payload<R: Read> {
+    Bzip2(Box<BzDecoder<Source<R>>>),
+    Raw(R),
+}
+
+/// Compressed input that remembers whether reading it failed
+///
+/// The decompressor passes errors from its input through unchanged, so this is how they're told
+/// apart from corrupt data.
+struct Source<R: Read> {
+    reader: R,
+    failed: bool,
+}
+
+impl<R: Read> Read for Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader
+            .read(buf)
+            .inspect_err(|e| self.failed |= e.kind() != io::ErrorKind::Interrupted)
+    }
+}
+
+impl<R: Read> Read for Payload<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Payload::Bzip2(decoder) => decoder.read(buf),
+            Payload::Raw(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Parse an `i32` without consuming it
+fn peek_i32(input: &[u8]) -> ParseResult<'_, i32> {
+    let (value, _) = take_i32(input)?;
+    Ok((value, input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Broken, product};
+
+    /// Read every radial of `input`, failing if the headers or any radial fails
+    fn read_all(input: impl Read) -> Result<Vec<Radial>, DiprError> {
+        DiprReader::new(input)?.collect()
+    }
+
+    #[test]
+    fn failed_reads_inside_the_compressed_data_are_io_errors() {
+        let product = product();
+        let e = read_all(Broken(&product[..product.len() - 10])).unwrap_err();
+        assert!(matches!(e.without_context(), DiprError::Io(_)), "{e:?}");
+    }
+
+    #[test]
+    fn corrupt_compressed_data_fails_decompression() {
+        let mut product = product();
+        // overwrite the magic number at the start of the first bzip2 block
+        product[HEADERS_LENGTH + 4..HEADERS_LENGTH + 10].fill(0);
+        let e = read_all(Broken(&product)).unwrap_err();
+        assert!(
+            matches!(e.without_context(), DiprError::DecompressionFailed(_)),
+            "{e:?}"
+        );
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    collections::BTreeMap,
+    f32::consts::PI,
+    fmt::{Debug, Display},
+    ops::RangeInclusive,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use geo::Point;
+use uom::si::{
+    angle::{degree, radian},
+    f32::{Angle, Length, Velocity},
+    length::meter,
+    velocity::meter_per_second,
+};
+
+use crate::{
+    DiprError, MessageHeader, OperationalMode, ParseOptions, PrecipRate, ProductDescription,
+    ProductDescriptionData, ProductType, Radial, RadialComponent, TextHeader, encode_dipr,
+    inch_per_hour,
+};
+
+/// Simple precipitation field that [`SynthConfig`] can sample
+///
+/// Positions are measured on a flat plane centered on the radar, which is plenty for test data.
+/// Patterns that move do so at a constant velocity starting from where they are at
+/// [`SynthConfig::capture_time`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrecipPattern {
+    /// Concentric rings around the radar station
+    ///
+    /// The rate is `peak` at the station and at every multiple of `spacing` from it, and it falls
+    /// smoothly to zero halfway between.
+    Rings {
+        /// Distance between the crests of neighboring rings
+        spacing: Length,
+        /// Rate at each crest
+        peak: Velocity,
+    },
+    /// Round storm cell whose rate falls off like a Gaussian from its center
+    Cell {
+        /// Bearing from the radar station to the center of the cell
+        azimuth: Angle,
+        /// Distance from the radar station to the center of the cell
+        range: Length,
+        /// Standard deviation of the Gaussian
+        radius: Length,
+        /// Rate at the center of the cell
+        peak: Velocity,
+        /// Direction in which the cell moves
+        heading: Angle,
+        /// How fast the cell moves
+        speed: Velocity,
+    },
+    /// Straight band of precipitation, like a squall line, that moves perpendicular to its length
+    Band {
+        /// Direction in which the band moves, which is perpendicular to the band itself
+        heading: Angle,
+        /// Signed distance from the radar station to the middle of the band, measured along
+        /// `heading`
+        offset: Length,
+        /// Distance across the band; the rate falls off like a Gaussian with a standard deviation
+        /// of half of this
+        width: Length,
+        /// Rate along the middle of the band
+        peak: Velocity,
+        /// How fast the band moves
+        speed: Velocity,
+    },
+}
+
+impl PrecipPattern {
+    /// Rate in in/hr at `x` meters east and `y` meters north of the radar station, `elapsed`
+    /// seconds after the capture time
+    fn rate(&self, x: f32, y: f32, elapsed: f32) -> f32 {
+        match self {
+            PrecipPattern::Rings { spacing, peak } => {
+                let distance = x.hypot(y);
+                let phase = 2. * PI * distance / spacing.get::<meter>();
+                peak.get::<inch_per_hour>() * 0.5 * (1. + phase.cos())
+            }
+            PrecipPattern::Cell {
+                azimuth,
+                range,
+                radius,
+                peak,
+                heading,
+                speed,
+            } => {
+                let (center_x, center_y) = polar(*azimuth, range.get::<meter>());
+                let (dx, dy) = polar(*heading, speed.get::<meter_per_second>() * elapsed);
+                let distance = (x - center_x - dx).hypot(y - center_y - dy);
+                gaussian(distance, radius.get::<meter>()) * peak.get::<inch_per_hour>()
+            }
+            PrecipPattern::Band {
+                heading,
+                offset,
+                width,
+                peak,
+                speed,
+            } => {
+                let (along_x, along_y) = polar(*heading, 1.);
+                let center = offset.get::<meter>() + speed.get::<meter_per_second>() * elapsed;
+                let distance = x * along_x + y * along_y - center;
+                gaussian(distance, width.get::<meter>() / 2.) * peak.get::<inch_per_hour>()
+            }
+        }
+    }
+}
+
+/// Fail with [`DiprError::Unencodable`] unless `actual` is within `expected`
+fn check<T: Debug + Display + PartialOrd>(
+    name: &'static str,
+    expected: RangeInclusive<T>,
+    actual: T,
+) -> Result<(), DiprError> {
+    if expected.contains(&actual) {
+        Ok(())
+    } else {
+        Err(DiprError::Unencodable(format!(
+            "{name}: got {actual}, expected {expected:?}"
+        )))
+    }
+}
+
+/// East and north components of a vector with the given bearing and length
+fn polar(bearing: Angle, length: f32) -> (f32, f32) {
+    let bearing = bearing.get::<radian>();
+    (length * bearing.sin(), length * bearing.cos())
+}
+
+/// Unnormalized Gaussian with a peak of 1
+fn gaussian(distance: f32, std_dev: f32) -> f32 {
+    (-(distance * distance) / (2. * std_dev * std_dev)).exp()
+}
+
+/// Description of a synthetic DIPR product
+///
+/// This is meant for tests and demos that can't use real products. Build a [`PrecipRate`] with
+/// [`SynthConfig::build`], or go straight to the native format with [`SynthConfig::encode`]. The
+/// [`Default`] implementation describes an empty product with the usual DIPR geometry at a
+/// fictional station.
+///
+/// ```
+/// use dipr::{PrecipPattern, SynthConfig, parse_dipr};
+/// use uom::si::{f32::{Length, Velocity}, length::kilometer};
+///
+/// # fn main() -> Result<(), dipr::DiprError> {
+/// let config = SynthConfig {
+///     patterns: vec![PrecipPattern::Rings {
+///         spacing: Length::new::<kilometer>(40.),
+///         peak: Velocity::new::<dipr::inch_per_hour>(2.),
+///     }],
+///     ..Default::default()
+/// };
+/// let dipr = parse_dipr(&config.encode()?)?;
+/// assert_eq!(dipr.radials, config.build()?.radials);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SynthConfig {
+    /// Four-letter code of the radar station
+    pub station_code: String,
+    /// Longitude/latitude coordinates of the radar station in degrees
+    pub location: Point<f64>,
+    /// Moment when the scan began
+    pub capture_time: DateTime<Utc>,
+    /// Incrementing counter to disambiguate scans, between 1 and 80 inclusive
+    pub scan_number: u8,
+    /// Number of radials, which evenly divide the full circle
+    ///
+    /// This must be between 180 and 800 inclusive, so that each radial is at most 2° wide.
+    pub num_radials: usize,
+    /// Number of bins in each radial, at most 1840
+    pub num_bins: usize,
+    /// Distance between the inner and outer extents of each bin measured radially
+    pub bin_size: Length,
+    /// Distance between the radar station and the center of the nearest bin
+    pub range_to_first_bin: Length,
+    /// Precipitation fields to sample at the center of each bin, which are added together
+    pub patterns: Vec<PrecipPattern>,
+}
+
+impl Default for SynthConfig {
+    fn default() -> Self {
+        SynthConfig {
+            station_code: "KXXX".to_string(),
+            location: Point::new(-96., 41.),
+            capture_time: DateTime::UNIX_EPOCH + TimeDelta::days(20_000),
+            scan_number: 1,
+            num_radials: 360,
+            num_bins: 920,
+            bin_size: Length::new::<meter>(250.),
+            range_to_first_bin: Length::new::<meter>(0.),
+            patterns: vec![],
+        }
+    }
+}
+
+impl SynthConfig {
+    const PRODUCT_TYPE: ProductType = ProductType::PrecipRate;
+    const VOLUME_COVERAGE_PATTERN: i32 = 212;
+
+    /// Sample the patterns and wrap the result in plausible headers
+    ///
+    /// Every rate is rounded to the thousandth of an in/hr that the format can store, so encoding
+    /// and parsing the result gives back the same radials. The message length and uncompressed
+    /// size in the headers are left as zero because [`encode_dipr`] computes them.
+    ///
+    /// Fails with [`DiprError::Unencodable`] if any setting is outside the range that
+    /// [`parse_dipr`](crate::parse_dipr) accepts, so the products always parse in
+    /// [`ParseMode::Strict`](crate::ParseMode::Strict) with the default limits.
+    pub fn build(&self) -> Result<PrecipRate, DiprError> {
+        self.validate()?;
+        Ok(self.build_at(0.))
+    }
+
+    /// Build the product as in [`SynthConfig::build`] and encode it with [`encode_dipr`]
+    pub fn encode(&self) -> Result<Vec<u8>, DiprError> {
+        encode_dipr(&self.build()?)
+    }
+
+    /// Build `count` consecutive products that are `interval` apart, starting with this one
+    ///
+    /// Moving patterns advance between products, and the scan number wraps from 80 back to 1. This
+    /// fails up front for the same settings as [`SynthConfig::build`].
+    pub fn series(
+        &self,
+        count: usize,
+        interval: TimeDelta,
+    ) -> Result<impl Iterator<Item = PrecipRate> + use<'_>, DiprError> {
+        self.validate()?;
+        Ok((0..count).map(move |idx| {
+            let elapsed = interval * idx as i32;
+            let config = SynthConfig {
+                capture_time: self.capture_time + elapsed,
+                scan_number: ((self.scan_number as usize - 1 + idx) % 80 + 1) as u8,
+                ..self.clone()
+            };
+            config.build_at(elapsed.num_milliseconds() as f32 / 1000.)
+        }))
+    }
+
+    /// Check every setting against the ranges that the parsers enforce
+    fn validate(&self) -> Result<(), DiprError> {
+        let max_width = *Radial::WIDTH_RANGE.end();
+        let min_radials = (360. / max_width).ceil() as usize;
+        let max_radials = (*RadialComponent::NUM_RADIALS_RANGE.end() as usize)
+            .min(ParseOptions::DEFAULT_MAX_RADIALS);
+        check("num radials", min_radials..=max_radials, self.num_radials)?;
+        let max_bins = (*Radial::NUM_BINS_RANGE.end() as usize).min(ParseOptions::DEFAULT_MAX_BINS);
+        check("num bins", 0..=max_bins, self.num_bins)?;
+        check(
+            "bin size",
+            RadialComponent::BIN_SIZE_RANGE,
+            self.bin_size.get::<meter>(),
+        )?;
+        check(
+            "range to first bin",
+            RadialComponent::RANGE_TO_FIRST_BIN_RANGE,
+            self.range_to_first_bin.get::<meter>(),
+        )?;
+        check(
+            "scan number",
+            ProductDescriptionData::SCAN_NUMBER_RANGE,
+            self.scan_number.into(),
+        )?;
+        // the product description block stores the location in thousandths of a degree
+        check(
+            "latitude",
+            ProductDescription::LATITUDE_RANGE,
+            (self.location.y() * 1000.).round() as i32,
+        )?;
+        check(
+            "longitude",
+            ProductDescription::LONGITUDE_RANGE,
+            (self.location.x() * 1000.).round() as i32,
+        )
+    }
+
+    fn build_at(&self, elapsed: f32) -> PrecipRate {
+        let width = 360. / self.num_radials as f32;
+        let radials = (0..self.num_radials)
+            .map(|radial_idx| {
+                let azimuth = Angle::new::<degree>(width * (radial_idx as f32 + 0.5));
+                let raw_values = (0..self.num_bins)
+                    .map(|bin_idx| {
+                        let range = self.range_to_first_bin.get::<meter>()
+                            + self.bin_size.get::<meter>() * bin_idx as f32;
+                        let (x, y) = polar(azimuth, range);
+                        let rate = self
+                            .patterns
+                            .iter()
+                            .map(|pattern| pattern.rate(x, y, elapsed))
+                            .sum::<f32>();
+                        Self::PRODUCT_TYPE.level(rate).into()
+                    })
+                    .collect::<Vec<u32>>();
+                Radial {
+                    azimuth,
+                    elevation: Angle::new::<degree>(0.5),
+                    width: Angle::new::<degree>(width),
+                    values: raw_values
+                        .iter()
+                        .map(|raw| Self::PRODUCT_TYPE.decode(*raw as u16))
+                        .collect(),
+                    raw_values,
+                    raw_attributes: String::new(),
+                    attributes: BTreeMap::new(),
+                }
+            })
+            .collect::<Vec<Radial>>();
+
+        let max_raw = radials
+            .iter()
+            .flat_map(|radial| radial.raw_values.iter().copied())
+            .max()
+            .unwrap_or(0);
+        let max_precip_rate =
+            Velocity::new::<inch_per_hour>(Self::PRODUCT_TYPE.decode(max_raw as u16).value());
+        let precip_detected = max_raw > 0;
+        let operational_mode = if precip_detected {
+            OperationalMode::Precipitation
+        } else {
+            OperationalMode::CleanAir
+        };
+
+        let station_code = self.station_code.clone();
+        let text_header = TextHeader {
+            data_designator: "SDUS53".to_string(),
+            originator: station_code.clone(),
+            issue_time: self.capture_time.format("%d%H%M").to_string(),
+            awips_id: format!("DPR{}", station_code.get(1..).unwrap_or_default()),
+        };
+        let message_header = MessageHeader {
+            message_code: Self::PRODUCT_TYPE.code(),
+            generation_time: self.capture_time,
+            length: 0,
+            source_id: 0,
+            destination_id: 0,
+            num_blocks: 3,
+        };
+        let product_description = ProductDescription {
+            location: self.location,
+            height: Length::new::<meter>(0.),
+            product_code: Self::PRODUCT_TYPE.code(),
+            operational_mode,
+            volume_coverage_pattern: Self::VOLUME_COVERAGE_PATTERN as i16,
+            sequence_number: 0,
+            volume_scan_number: self.scan_number.into(),
+            volume_scan_time: self.capture_time,
+            generation_time: self.capture_time,
+            elevation_number: 0,
+            product_dependent: [0; 10],
+            data_level_thresholds: [0; 16],
+            version: 0,
+            spot_blank: false,
+            // in halfwords from the start of the message header
+            symbology_offset: ((MessageHeader::LENGTH + ProductDescription::LENGTH) / 2) as u32,
+            graphic_offset: 0,
+            tabular_offset: 0,
+            precip_detected,
+            max_precip_rate,
+            compression_method: ProductDescription::BZIP2_COMPRESSION,
+            uncompressed_size: 0,
+        };
+        let description_data = ProductDescriptionData {
+            name: "DPR".to_string(),
+            description: "Digital Instantaneous Precipitation Rate".to_string(),
+            product_code: Self::PRODUCT_TYPE.code().into(),
+            product_type: 1,
+            generation_time: self.capture_time,
+            radar_name: station_code.clone(),
+            radar_location: self.location,
+            radar_height: Length::new::<meter>(0.),
+            volume_scan_start_time: self.capture_time,
+            volume_scan_end_time: self.capture_time,
+            elevation_angle: Angle::new::<degree>(0.),
+            volume_scan_number: self.scan_number.into(),
+            operational_mode,
+            volume_coverage_pattern: Self::VOLUME_COVERAGE_PATTERN,
+            elevation_number: 0,
+            compression_type: 0,
+            decompressed_size: 0,
+            parameters: BTreeMap::new(),
+        };
+
+        PrecipRate {
+            station_code,
+            product_type: Self::PRODUCT_TYPE,
+            text_header,
+            message_header,
+            capture_time: self.capture_time,
+            scan_number: self.scan_number,
+            location: self.location,
+            operational_mode,
+            precip_detected,
+            max_precip_rate,
+            bin_size: self.bin_size,
+            range_to_first_bin: self.range_to_first_bin,
+            radials,
+            radial_component: RadialComponent {
+                description: "Precipitation Rate".to_string(),
+                bin_size: self.bin_size,
+                range_to_first_bin: self.range_to_first_bin,
+                parameters: BTreeMap::new(),
+                radials: vec![],
+            },
+            radial_component_index: 0,
+            components: vec![],
+            product_description,
+            description_data,
+            wrappers: vec![],
+            warnings: vec![],
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{DiprError, ParseResult, utils::*};
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+/// WMO communications header that precedes the product
+///
+/// This is the 30-byte block of text at the start of the file, e.g.,
+/// `SDUS53 KOAX 011204\r\r\nDPROAX\r\r\n`. The first line is the WMO abbreviated heading, and the
+/// second line is the AWIPS identifier.
+pub struct TextHeader {
+    /// WMO data type and geographical designator (`TTAAii`), e.g., `SDUS53`
+    pub data_designator: String,
+    /// WMO originating office (`CCCC`), which is the four-letter radar station code for DIPR
+    pub originator: String,
+    /// Day of month, hour, and minute when the product was issued (`YYGGgg`), e.g., `011204`
+    pub issue_time: String,
+    /// AWIPS product category and location identifier (`NNNxxx`), e.g., `DPROAX`
+    pub awips_id: String,
+}
+
+impl TextHeader {
+    pub(crate) const NAME: &'static str = "text header";
+    pub(crate) const LENGTH: usize = 30;
+
+    /// WMO abbreviated heading as it appears in the file, e.g., `SDUS53 KOAX 011204`
+    pub fn wmo_heading(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.data_designator, self.originator, self.issue_time
+        )
+    }
+}
+
+/// Parse the WMO text header
+pub(crate) fn text_header(input: &[u8]) -> ParseResult<'_, TextHeader> {
+    let (data_designator, tail) = take_ascii(input, 6)?;
+    let (_, tail) = take_bytes(tail, 1)?;
+    let (originator, tail) = take_ascii(tail, 4)?;
+    let (_, tail) = take_bytes(tail, 1)?;
+    let (issue_time, tail) = take_ascii(tail, 6)?;
+    let (_, tail) = take_bytes(tail, 3)?; // \r\r\n
+    let (awips_id, tail) = take_ascii(tail, 6)?;
+    let (_, tail) = take_bytes(tail, 3)?; // \r\r\n
+    Ok((
+        TextHeader {
+            data_designator,
+            originator,
+            issue_time,
+            awips_id,
+        },
+        tail,
+    ))
+}
+
+/// Encode the WMO text header
+///
+/// Fields that are shorter than their fixed width are padded with spaces.
+pub(crate) fn encode_text_header(header: &TextHeader, out: &mut Vec<u8>) -> Result<(), DiprError> {
+    put_ascii(out, &header.data_designator, 6, "data designator")?;
+    out.push(b' ');
+    put_ascii(out, &header.originator, 4, "originator")?;
+    out.push(b' ');
+    put_ascii(out, &header.issue_time, 6, "issue time")?;
+    out.extend_from_slice(b"\r\r\n");
+    put_ascii(out, &header.awips_id, 6, "AWIPS ID")?;
+    out.extend_from_slice(b"\r\r\n");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &[u8; 30] = b"SDUS53 KOAX 011204\r\r\nDPROAX\r\r\n";
+
+    #[test]
+    fn header_fields_are_split_out() {
+        let input = [HEADER.as_slice(), &[0xff]].concat();
+        let (header, tail) = text_header(&input).unwrap();
+        assert_eq!(
+            header,
+            TextHeader {
+                data_designator: "SDUS53".to_string(),
+                originator: "KOAX".to_string(),
+                issue_time: "011204".to_string(),
+                awips_id: "DPROAX".to_string(),
+            }
+        );
+        assert_eq!(header.wmo_heading(), "SDUS53 KOAX 011204");
+        assert_eq!(tail, [0xff]);
+
+        let mut out = vec![];
+        encode_text_header(&header, &mut out).unwrap();
+        assert_eq!(out, HEADER);
+    }
+
+    #[test]
+    fn invalid_text_is_an_error() {
+        let mut input = *HEADER;
+        input[7] = 0xff;
+        let e = text_header(&input).unwrap_err();
+        assert!(
+            matches!(e.without_context(), DiprError::InvalidUtf8String(_)),
+            "{e:?}"
+        );
+        assert_eq!(e.context().unwrap().remaining, Some(HEADER.len() - 7));
+    }
+
+    #[test]
+    fn short_fields_are_padded_and_long_ones_rejected() {
+        let mut header = text_header(HEADER).unwrap().0;
+        header.awips_id = "DPR".to_string();
+        let mut out = vec![];
+        encode_text_header(&header, &mut out).unwrap();
+        assert_eq!(&out[21..27], b"DPR   ");
+
+        header.originator = "KOAXX".to_string();
+        assert!(matches!(
+            encode_text_header(&header, &mut vec![]),
+            Err(DiprError::Unencodable(_))
+        ));
+    }
+}
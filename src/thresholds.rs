@@ -0,0 +1,185 @@
+use std::fmt::Display;
+
+use crate::{ProductType, Quantity};
+
+/// Mapping from the data levels of a digital radial product to physical values
+///
+/// Digital radial products store every bin as an 8-bit data level. The levels below
+/// [`Thresholds::first_data_level`] are reserved for bins below the threshold and bins with no
+/// data, and the product description block describes what the rest stand for in its data level
+/// threshold halfwords (Table V, see
+/// [`ProductDescription::thresholds`](crate::ProductDescription::thresholds)). Values are given in
+/// the unit of the product's [`Quantity`](crate::Quantity).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Thresholds {
+    /// Evenly spaced values, as used by the reflectivity products
+    Linear {
+        /// Value of the first data level (halfword 31, in tenths)
+        minimum: f32,
+        /// Difference between consecutive data levels (halfword 32, in tenths)
+        increment: f32,
+        /// Number of data levels (halfword 33)
+        num_levels: u16,
+    },
+    /// Evenly spaced values up to `log_start` and exponentially spaced ones from there on, as used
+    /// by digital VIL
+    ///
+    /// Every field except `log_start` is stored as a 16-bit float.
+    LinearLog {
+        /// Data levels per unit in the linear part (halfword 31)
+        linear_scale: f32,
+        /// Data level that stands for zero in the linear part (halfword 32)
+        linear_offset: f32,
+        /// First data level of the exponential part (halfword 33)
+        log_start: u16,
+        /// Data levels per factor of _e_ in the exponential part (halfword 34)
+        log_scale: f32,
+        /// Data level that stands for one in the exponential part (halfword 35)
+        log_offset: f32,
+    },
+    /// Data levels that are a linear function of the value, as used by the digital accumulation
+    /// products
+    ///
+    /// A data level is `scale * value + offset`. Both are stored as 32-bit floats across two
+    /// halfwords, and the products scale hundredths of an inch, so `scale` is converted to data
+    /// levels per inch here.
+    ScaleOffset {
+        /// Data levels per unit (halfwords 31 and 32)
+        scale: f32,
+        /// Data level that stands for zero (halfwords 33 and 34)
+        offset: f32,
+    },
+}
+
+impl Thresholds {
+    /// First data level that stands for a value rather than a flag in the reflectivity and VIL
+    /// products
+    pub const FIRST_DATA_LEVEL: u8 = 2;
+    /// First data level that stands for a value in the digital accumulation products, which don't
+    /// reserve any because level 0 means that nothing accumulated
+    const FIRST_ACCUMULATION_LEVEL: u8 = 0;
+    /// Hundredths of an inch per inch, the unit that the digital accumulation products scale
+    const ACCUMULATION_UNITS_PER_INCH: f32 = 100.;
+
+    /// First data level of a product of type `product_type` that stands for a value rather than a
+    /// flag
+    ///
+    /// For the reflectivity and VIL products, level 0 means that the bin is below the threshold,
+    /// and level 1 means that it has no data. The accumulation products have no such flags, so
+    /// this is 0 for them.
+    pub fn first_data_level(product_type: ProductType) -> u8 {
+        match product_type.quantity() {
+            Quantity::Accumulation => Self::FIRST_ACCUMULATION_LEVEL,
+            _ => Self::FIRST_DATA_LEVEL,
+        }
+    }
+
+    /// Decode the threshold halfwords of a product of type `product_type`, or [`None`] if it isn't
+    /// a digital radial product
+    pub(crate) fn from_halfwords(product_type: ProductType, halfwords: &[i16; 16]) -> Option<Self> {
+        match product_type {
+            ProductType::DigitalHybridReflectivity | ProductType::DigitalReflectivity => {
+                Some(Thresholds::Linear {
+                    minimum: halfwords[0] as f32 / 10.,
+                    increment: halfwords[1] as f32 / 10.,
+                    num_levels: halfwords[2] as u16,
+                })
+            }
+            ProductType::DigitalVil => Some(Thresholds::LinearLog {
+                linear_scale: float16(halfwords[0] as u16),
+                linear_offset: float16(halfwords[1] as u16),
+                log_start: halfwords[2] as u16,
+                log_scale: float16(halfwords[3] as u16),
+                log_offset: float16(halfwords[4] as u16),
+            }),
+            ProductType::DigitalAccumulationArray
+            | ProductType::DigitalStormTotalAccumulation
+            | ProductType::DigitalUserSelectableAccumulation => Some(Thresholds::ScaleOffset {
+                scale: float32(halfwords[0], halfwords[1]) * Self::ACCUMULATION_UNITS_PER_INCH,
+                offset: float32(halfwords[2], halfwords[3]),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Convert a data level into the value that it stands for
+    ///
+    /// Levels below [`Thresholds::first_data_level`] don't stand for a value, so the result is
+    /// meaningless for them. Level 0 of an accumulation product is exactly zero no matter what
+    /// the offset says.
+    pub fn decode(&self, level: u8) -> f32 {
+        if level == 0 && matches!(self, Thresholds::ScaleOffset { .. }) {
+            return 0.;
+        }
+        let level = level as f32;
+        match *self {
+            Thresholds::Linear {
+                minimum, increment, ..
+            } => minimum + (level - Self::FIRST_DATA_LEVEL as f32) * increment,
+            Thresholds::LinearLog {
+                linear_scale,
+                linear_offset,
+                log_start,
+                log_scale,
+                log_offset,
+            } => {
+                if level < log_start as f32 {
+                    (level - linear_offset) / linear_scale
+                } else {
+                    ((level - log_offset) / log_scale).exp()
+                }
+            }
+            Thresholds::ScaleOffset { scale, offset } => (level - offset) / scale,
+        }
+    }
+}
+
+impl Display for Thresholds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Thresholds::Linear {
+                minimum,
+                increment,
+                num_levels,
+            } => write!(
+                f,
+                "{num_levels} levels from {minimum:.1} in steps of {increment:.1}"
+            ),
+            Thresholds::LinearLog {
+                linear_scale,
+                linear_offset,
+                log_start,
+                log_scale,
+                log_offset,
+            } => write!(
+                f,
+                "linear (scale {linear_scale:.3}, offset {linear_offset:.3}) below level \
+                 {log_start}, then exponential (scale {log_scale:.3}, offset {log_offset:.3})"
+            ),
+            Thresholds::ScaleOffset { scale, offset } => {
+                write!(f, "linear (scale {scale:.3}, offset {offset:.3})")
+            }
+        }
+    }
+}
+
+/// Decode an IEEE 754 single precision float that's split across two halfwords, high one first
+fn float32(high: i16, low: i16) -> f32 {
+    f32::from_bits(((high as u16 as u32) << 16) | low as u16 as u32)
+}
+
+/// Decode the 16-bit float format of the threshold halfwords
+///
+/// The high bit is the sign, the next five bits are the exponent, and the low ten bits are the
+/// fraction. Unlike IEEE 754 half precision, the exponent is biased by 16 and there are no
+/// special values.
+fn float16(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1. } else { 1. };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let fraction = (bits & 0x3ff) as f32 / 1024.;
+    if exponent == 0 {
+        sign * 2. * fraction
+    } else {
+        sign * 2f32.powi(exponent - 16) * (1. + fraction)
+    }
+}
@@ -1,39 +1,67 @@
 use std::{
     fmt::{Debug, Display},
+    mem::size_of,
     ops::RangeInclusive,
 };
 
-use crate::{DiprError, ParseResult};
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    DiprError, ParseMode, ParseOptions, ParseResult, ParseWarning, Stream, components::in_radial,
+};
 
 /// Pop `n` bytes off the front of `input` and return the two pieces
-pub(crate) fn take_bytes(input: &[u8], n: u16) -> ParseResult<&[u8]> {
-    let x = input.split_at(n as usize);
-    Ok((x.0, x.1))
+///
+/// If `input` is shorter than `n`, this returns [`DiprError::Truncated`] without a section or
+/// offset. The top-level parser fills those in with [`DiprError::locate`].
+pub(crate) fn take_bytes(input: &[u8], n: usize) -> ParseResult<'_, &[u8]> {
+    match input.split_at_checked(n) {
+        Some(x) => Ok(x),
+        None => Err(DiprError::Truncated {
+            section: "",
+            offset: 0,
+            needed: n,
+            available: input.len(),
+        }),
+    }
 }
 
 /// Consume one byte from `input` and parse an `i8`
-pub(crate) fn take_i8(input: &[u8]) -> ParseResult<i8> {
+pub(crate) fn take_i8(input: &[u8]) -> ParseResult<'_, i8> {
     let (number, tail) = take_bytes(input, 1)?;
     let buf: [u8; 1] = number.try_into()?;
     Ok((i8::from_be_bytes(buf), tail))
 }
 
+/// Consume one byte from `input` and parse a `u8`
+pub(crate) fn take_u8(input: &[u8]) -> ParseResult<'_, u8> {
+    let (number, tail) = take_bytes(input, 1)?;
+    Ok((number[0], tail))
+}
+
 /// Consume two bytes from `input` and parse an `i16`
-pub(crate) fn take_i16(input: &[u8]) -> ParseResult<i16> {
+pub(crate) fn take_i16(input: &[u8]) -> ParseResult<'_, i16> {
     let (number, tail) = take_bytes(input, 2)?;
     let buf: [u8; 2] = number.try_into()?;
     Ok((i16::from_be_bytes(buf), tail))
 }
 
+/// Consume two bytes from `input` and parse a `u16`
+pub(crate) fn take_u16(input: &[u8]) -> ParseResult<'_, u16> {
+    let (number, tail) = take_bytes(input, 2)?;
+    let buf: [u8; 2] = number.try_into()?;
+    Ok((u16::from_be_bytes(buf), tail))
+}
+
 /// Consume four bytes from `input` and parse an `i32`
-pub(crate) fn take_i32(input: &[u8]) -> ParseResult<i32> {
+pub(crate) fn take_i32(input: &[u8]) -> ParseResult<'_, i32> {
     let (number, tail) = take_bytes(input, 4)?;
     let buf: [u8; 4] = number.try_into()?;
     Ok((i32::from_be_bytes(buf), tail))
 }
 
 /// Consume four bytes from `input` and parse a `u32`
-pub(crate) fn take_u32(input: &[u8]) -> ParseResult<u32> {
+pub(crate) fn take_u32(input: &[u8]) -> ParseResult<'_, u32> {
     let (number, tail) = take_bytes(input, 4)?;
     let buf: [u8; 4] = number.try_into()?;
     Ok((u32::from_be_bytes(buf), tail))
@@ -46,53 +74,323 @@ pub(crate) fn take_u32(input: &[u8]) -> ParseResult<u32> {
 /// of the string follow, padded with zero bytes to a multiple of four.
 ///
 /// For more information, see [RFC 1832](https://datatracker.ietf.org/doc/html/rfc1832#section-3.11).
-pub(crate) fn take_string(input: &[u8]) -> ParseResult<String> {
+pub(crate) fn take_string(input: &[u8]) -> ParseResult<'_, String> {
+    let (string, tail) = take_str(input)?;
+    Ok((string.to_string(), tail))
+}
+
+/// Parse an XDR string from the head of the input without copying it
+///
+/// See [`take_string`] for details on the format.
+pub(crate) fn take_str(input: &[u8]) -> ParseResult<'_, &str> {
     let (length, tail) = take_u32(input)?;
     // grab the string
-    let (string_bytes, tail) = take_bytes(tail, length as u16)?;
-    let string = String::from_utf8(string_bytes.to_vec())?;
+    let (string_bytes, tail) = take_bytes(tail, length as usize)?;
+    let string = str::from_utf8(string_bytes).map_err(|_| {
+        // redo the conversion to get the owned error type that DiprError wraps; this can't succeed
+        // since the bytes are the same
+        let e = String::from_utf8(string_bytes.to_vec()).unwrap_err();
+        DiprError::from(e).with_context(|c| c.remaining = Some(input.len()))
+    })?;
     // pad out to the next four-byte boundary if needed
     if length % 4 != 0 {
-        let (_, tail) = take_bytes(tail, (4 - (length % 4)) as u16)?;
+        let (_, tail) = take_bytes(tail, (4 - (length % 4)) as usize)?;
         Ok((string, tail))
     } else {
         Ok((string, tail))
     }
 }
 
+/// Consume `n` bytes from `input` and parse them as a fixed-length text field
+pub(crate) fn take_ascii(input: &[u8], n: usize) -> ParseResult<'_, String> {
+    let (string_bytes, tail) = take_bytes(input, n)?;
+    let string = String::from_utf8(string_bytes.to_vec())
+        .map_err(|e| DiprError::from(e).with_context(|c| c.remaining = Some(input.len())))?;
+    Ok((string, tail))
+}
+
 /// Consume four bytes from `input` and parse an `f32`
-pub(crate) fn take_float(input: &[u8]) -> ParseResult<f32> {
+pub(crate) fn take_float(input: &[u8]) -> ParseResult<'_, f32> {
     let (number, tail) = take_bytes(input, 4)?;
     let buf: [u8; 4] = number.try_into()?;
     Ok((f32::from_be_bytes(buf), tail))
 }
 
+/// Convert a date and time as they appear in the product headers into a timestamp
+///
+/// `date` counts days where 1 is 1 January 1970, and `seconds` counts seconds since midnight UTC.
+pub(crate) fn julian_date_time(date: u16, seconds: u32) -> DateTime<Utc> {
+    DateTime::UNIX_EPOCH + TimeDelta::days(date as i64 - 1) + TimeDelta::seconds(seconds as i64)
+}
+
+/// Inverse of [`julian_date_time`]
+///
+/// Fails if `time` is before the first representable day or too far in the future.
+pub(crate) fn to_julian_date_time(
+    time: DateTime<Utc>,
+    name: &'static str,
+) -> Result<(u16, u32), DiprError> {
+    const SECONDS_PER_DAY: i64 = 86_400;
+    let seconds = time.timestamp();
+    let date = u16::try_from(seconds.div_euclid(SECONDS_PER_DAY) + 1)
+        .map_err(|_| DiprError::Unencodable(format!("{name}: {time} is out of range")))?;
+    Ok((date, seconds.rem_euclid(SECONDS_PER_DAY) as u32))
+}
+
+/// Convert a timestamp into the seconds since the Unix epoch that the symbology block uses
+pub(crate) fn to_unix_seconds(time: DateTime<Utc>, name: &'static str) -> Result<u32, DiprError> {
+    u32::try_from(time.timestamp())
+        .map_err(|_| DiprError::Unencodable(format!("{name}: {time} is out of range")))
+}
+
+/// Append a big-endian `i16` to `out`
+pub(crate) fn put_i16(out: &mut Vec<u8>, value: i16) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Append a big-endian `u16` to `out`
+pub(crate) fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Append a big-endian `i32` to `out`
+pub(crate) fn put_i32(out: &mut Vec<u8>, value: i32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Append a big-endian `u32` to `out`
+pub(crate) fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Append a big-endian `f32` to `out`
+pub(crate) fn put_float(out: &mut Vec<u8>, value: f32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Append an XDR string to `out`
+///
+/// This is the inverse of [`take_string`].
+pub(crate) fn put_string(out: &mut Vec<u8>, string: &str) {
+    put_u32(out, string.len() as u32);
+    out.extend_from_slice(string.as_bytes());
+    out.resize(out.len() + (4 - string.len() % 4) % 4, 0);
+}
+
+/// Append a fixed-length text field to `out`, padding it with spaces
+///
+/// This is the inverse of [`take_ascii`]. Fails if `string` is longer than `n` bytes.
+pub(crate) fn put_ascii(
+    out: &mut Vec<u8>,
+    string: &str,
+    n: usize,
+    name: &'static str,
+) -> Result<(), DiprError> {
+    if string.len() > n {
+        return Err(DiprError::Unencodable(format!(
+            "{name}: {string:?} is longer than {n} bytes"
+        )));
+    }
+    out.extend_from_slice(string.as_bytes());
+    out.resize(out.len() + n - string.len(), b' ');
+    Ok(())
+}
+
+/// Check that a parsed value equals its only acceptable value
+///
+/// `tail` is the input immediately following the value, which is used to find the value's offset
+/// in the stream if the check fails.
 pub(crate) fn check_value<T: Display + PartialEq>(
     expected: T,
     actual: T,
-    name: &str,
-    func: &str,
+    name: &'static str,
+    func: &'static str,
+    tail: &[u8],
 ) -> Result<(), DiprError> {
     if expected != actual {
-        Err(DiprError::ValueOutOfRange(format!(
-            "{name} in {func}: got {actual}, expected {expected}"
-        )))
+        Err(out_of_range(
+            format!("{name} in {func}: got {actual}, expected {expected}"),
+            name,
+            func,
+            tail.len() + size_of::<T>(),
+        ))
     } else {
         Ok(())
     }
 }
 
-pub(crate) fn check_range_inclusive<T: Debug + Display + PartialOrd>(
-    expected: RangeInclusive<T>,
-    actual: T,
-    name: &str,
-    func: &str,
-) -> Result<(), DiprError> {
-    if !expected.contains(&actual) {
-        Err(DiprError::ValueOutOfRange(format!(
-            "{name} in {func}: got {actual}, expected {expected:?}"
-        )))
-    } else {
+/// Applies the range checks from the specification according to [`ParseOptions`]
+///
+/// Every parser that checks a range takes one of these. In [`ParseMode::Strict`], an out-of-range
+/// value is an error. In [`ParseMode::Lenient`], it's recorded and parsing continues. Recorded
+/// values can't be turned into [`ParseWarning`]s right away because their offsets are only known
+/// relative to the end of the parser's input, so each caller that locates errors with
+/// [`DiprError::locate`] must also call [`Validator::locate_warnings`].
+pub(crate) struct Validator {
+    options: ParseOptions,
+    /// Out-of-range values that were tolerated but not yet located
+    pending: Vec<DiprError>,
+    warnings: Vec<ParseWarning>,
+}
+
+impl Validator {
+    pub(crate) fn new(options: &ParseOptions) -> Self {
+        Validator {
+            options: options.clone(),
+            pending: vec![],
+            warnings: vec![],
+        }
+    }
+
+    pub(crate) fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Check that a parsed value is within its acceptable range
+    ///
+    /// `tail` is used the same way as in [`check_value`].
+    pub(crate) fn check_range_inclusive<T: Debug + Display + PartialOrd>(
+        &mut self,
+        expected: RangeInclusive<T>,
+        actual: T,
+        name: &'static str,
+        func: &'static str,
+        tail: &[u8],
+    ) -> Result<(), DiprError> {
+        if !expected.contains(&actual) {
+            let e = out_of_range(
+                format!("{name} in {func}: got {actual}, expected {expected:?}"),
+                name,
+                func,
+                tail.len() + size_of::<T>(),
+            );
+            match self.options.mode {
+                ParseMode::Strict => return Err(e),
+                ParseMode::Lenient => self.pending.push(e),
+            }
+        }
         Ok(())
     }
+
+    /// Convert a parsed count to `usize`, failing if it's negative or larger than `limit`
+    ///
+    /// Unlike the range checks, this applies in every [`ParseMode`] because the count decides how
+    /// much memory is allocated. `tail` is used the same way as in [`check_value`].
+    pub(crate) fn check_count(
+        &self,
+        limit: usize,
+        actual: i32,
+        name: &'static str,
+        func: &'static str,
+        tail: &[u8],
+    ) -> Result<usize, DiprError> {
+        match usize::try_from(actual) {
+            Ok(count) if count <= limit => Ok(count),
+            Ok(count) => Err(DiprError::LimitExceeded {
+                name,
+                limit,
+                actual: count,
+            }
+            .with_context(|c| {
+                c.section = func;
+                c.field = Some(name);
+                c.remaining = Some(tail.len() + size_of::<i32>());
+            })),
+            Err(_) => Err(out_of_range(
+                format!("{name} in {func}: got {actual}, expected a count"),
+                name,
+                func,
+                tail.len() + size_of::<i32>(),
+            )),
+        }
+    }
+
+    /// Compare the size of the decompressed payload with the size that the header advertised
+    pub(crate) fn check_uncompressed_size(
+        &mut self,
+        expected: u32,
+        actual: usize,
+    ) -> Result<(), DiprError> {
+        if actual != expected as usize {
+            match self.options.mode {
+                ParseMode::Strict => {
+                    return Err(DiprError::UncompressedSizeMismatch { expected, actual });
+                }
+                ParseMode::Lenient => self
+                    .warnings
+                    .push(ParseWarning::UncompressedSizeMismatch { expected, actual }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Run `parser` and attribute any errors or warnings that it produces to the radial at
+    /// `radial_idx`
+    pub(crate) fn in_radial<T>(
+        &mut self,
+        radial_idx: usize,
+        parser: impl FnOnce(&mut Self) -> Result<T, DiprError>,
+    ) -> Result<T, DiprError> {
+        let start = self.pending.len();
+        let result = parser(self).map_err(|e| in_radial(e, radial_idx));
+        let pending = self
+            .pending
+            .drain(start..)
+            .map(|e| in_radial(e, radial_idx))
+            .collect::<Vec<_>>();
+        self.pending.extend(pending);
+        result
+    }
+
+    /// Turn the values recorded since the last call into warnings located in `stream`
+    ///
+    /// The arguments have the same meaning as in [`DiprError::locate`].
+    pub(crate) fn locate_warnings(
+        &mut self,
+        section: &'static str,
+        stream: Stream,
+        stream_len: usize,
+    ) {
+        for e in self.pending.drain(..) {
+            let DiprError::Context { context, source } = e.locate(section, stream, stream_len)
+            else {
+                unreachable!("locate always returns DiprError::Context");
+            };
+            let message = match *source {
+                DiprError::ValueOutOfRange(message) => message,
+                e => e.to_string(),
+            };
+            self.warnings
+                .push(ParseWarning::ValueOutOfRange { context, message });
+        }
+    }
+
+    /// Forget the values recorded since the last call to [`Validator::locate_warnings`]
+    ///
+    /// This is for parsers that are rerun on the same input after more of it arrives.
+    pub(crate) fn discard_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Warnings located so far
+    pub(crate) fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    pub(crate) fn into_warnings(self) -> Vec<ParseWarning> {
+        self.warnings
+    }
+}
+
+fn out_of_range(
+    message: String,
+    name: &'static str,
+    func: &'static str,
+    remaining: usize,
+) -> DiprError {
+    DiprError::ValueOutOfRange(message).with_context(|c| {
+        c.section = func;
+        c.field = Some(name);
+        c.remaining = Some(remaining);
+    })
 }
@@ -0,0 +1,119 @@
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    io::{self, Read},
+};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::{DiprError, ProductDescription};
+
+/// Container or variant of the native format that was found around a DIPR product
+///
+/// [`parse_dipr`](crate::parse_dipr) and friends detect these automatically and record them
+/// outermost first, e.g., `[Gzip, NoaaPort]` for a gzip file that holds a NOAAPort frame.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Wrapper {
+    /// gzip compression around the whole product, as in `.gz` files from archives
+    Gzip,
+    /// SBN/NOAAPort framing, i.e., a start-of-header byte and sequence number before the text
+    /// header and an end-of-text byte after the product
+    NoaaPort,
+    /// Product symbology block stored without the usual bzip2 compression
+    ///
+    /// Unlike the other variants, this isn't around the product but inside it. The uncompressed
+    /// size in the product description block isn't checked for these products.
+    UncompressedSymbology,
+}
+
+impl Wrapper {
+    /// Most containers to remove from one input, which stops a file that unpacks to itself
+    const MAX_DEPTH: usize = 4;
+    pub(crate) const GZIP_MAGIC: &'static [u8] = &[0x1f, 0x8b];
+    const BZIP2_MAGIC: &'static [u8] = b"BZh";
+    /// Block divider at the start of an uncompressed product symbology block
+    const BLOCK_DIVIDER: &'static [u8] = &[0xff, 0xff];
+    const SOH: u8 = 0x01;
+    const ETX: u8 = 0x03;
+}
+
+impl Display for Wrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Wrapper::Gzip => write!(f, "gzip"),
+            Wrapper::NoaaPort => write!(f, "NOAAPort"),
+            Wrapper::UncompressedSymbology => write!(f, "uncompressed symbology"),
+        }
+    }
+}
+
+/// Remove any gzip compression and NOAAPort framing from around a product
+///
+/// This returns the bare product along with the containers that were removed, outermost first.
+/// Decompressed data is limited to `limit` bytes like the product symbology block.
+pub(crate) fn unwrap_input(
+    input: &[u8],
+    limit: usize,
+) -> Result<(Cow<'_, [u8]>, Vec<Wrapper>), DiprError> {
+    let mut product = Cow::Borrowed(input);
+    let mut wrappers = vec![];
+    while wrappers.len() < Wrapper::MAX_DEPTH {
+        if product.starts_with(Wrapper::GZIP_MAGIC) {
+            product = Cow::Owned(gunzip(&product, limit)?);
+            wrappers.push(Wrapper::Gzip);
+        } else if product.first() == Some(&Wrapper::SOH) {
+            product = match product {
+                Cow::Borrowed(p) => Cow::Borrowed(strip_noaaport(p)),
+                Cow::Owned(p) => Cow::Owned(strip_noaaport(&p).to_vec()),
+            };
+            wrappers.push(Wrapper::NoaaPort);
+        } else {
+            break;
+        }
+    }
+    Ok((product, wrappers))
+}
+
+/// Decompress a gzip file, which may have several members
+fn gunzip(input: &[u8], limit: usize) -> Result<Vec<u8>, DiprError> {
+    // read one byte past the limit so that we can tell whether it was exceeded
+    let mut output = vec![];
+    let mut reader = MultiGzDecoder::new(input).take(limit as u64 + 1);
+    io::copy(&mut reader, &mut output).map_err(|source| DiprError::UnwrapFailed {
+        wrapper: Wrapper::Gzip,
+        source,
+    })?;
+    if output.len() > limit {
+        return Err(DiprError::PayloadTooLarge { limit });
+    }
+    Ok(output)
+}
+
+/// Remove the NOAAPort frame from a product that starts with a start-of-header byte
+///
+/// The frame looks like `\x01\r\r\n123 \r\r\n` before the text header and `\r\r\n\x03` after the
+/// product. Anything that doesn't match is left in place for the parsers to reject.
+fn strip_noaaport(input: &[u8]) -> &[u8] {
+    let start = input[1..]
+        .iter()
+        .position(|b| !matches!(b, b'\r' | b'\n' | b' ' | b'0'..=b'9'))
+        .map_or(input.len(), |p| p + 1);
+    let mut product = &input[start..];
+    if let Some(p) = product.strip_suffix(&[Wrapper::ETX]) {
+        product = p.strip_suffix(b"\r\r\n").unwrap_or(p);
+    }
+    product
+}
+
+/// Whether `symbology`, which follows the product description block, is stored uncompressed
+///
+/// The compression method isn't always set correctly, so the data itself is checked too.
+pub(crate) fn is_uncompressed(description: &ProductDescription, symbology: &[u8]) -> bool {
+    !symbology.starts_with(Wrapper::BZIP2_MAGIC)
+        && (description.compression_method == ProductDescription::NO_COMPRESSION
+            || symbology.starts_with(Wrapper::BLOCK_DIVIDER))
+}
+
+/// Number of bytes that [`is_uncompressed`] needs to see
+pub(crate) const SNIFF_LENGTH: usize = Wrapper::BZIP2_MAGIC.len();
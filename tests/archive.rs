@@ -0,0 +1,94 @@
+mod common;
+
+use chrono::TimeDelta;
+use dipr::{ArchiveFilter, DiprArchive, DiprError, ParseOptions, SynthConfig, Wrapper};
+
+fn product(station_code: &str, minutes: i64) -> Vec<u8> {
+    let config = common::config();
+    SynthConfig {
+        station_code: station_code.to_string(),
+        capture_time: config.capture_time + TimeDelta::minutes(minutes),
+        ..config
+    }
+    .encode()
+    .unwrap()
+}
+
+/// Tar archive with a directory, three products, and a file that isn't a product
+fn archive() -> Vec<u8> {
+    let mut builder = tar::Builder::new(vec![]);
+    let mut append = |name: &str, data: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, data).unwrap();
+    };
+    append("KOAX/a", &product("KOAX", 0));
+    append("KOAX/b.gz", &common::gzip(&product("KOAX", 10)));
+    append("KDMX/c", &product("KDMX", 20));
+    append("README", b"not a product");
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Directory);
+    header.set_size(0);
+    header.set_mode(0o755);
+    header.set_cksum();
+    builder.append_data(&mut header, "empty/", &[][..]).unwrap();
+    builder.into_inner().unwrap()
+}
+
+fn names(archive: &[u8], filter: ArchiveFilter) -> Vec<String> {
+    let mut archive = DiprArchive::with_options(archive, &ParseOptions::default(), filter).unwrap();
+    archive
+        .products()
+        .unwrap()
+        .filter(|(_, dipr)| dipr.is_ok())
+        .map(|(name, _)| name)
+        .collect()
+}
+
+#[test]
+fn archive_yields_every_product_and_error() {
+    for archive in [archive(), common::gzip(&archive())] {
+        let mut archive = DiprArchive::new(archive.as_slice()).unwrap();
+        let products = archive.products().unwrap().collect::<Vec<_>>();
+        let names = products.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["KOAX/a", "KOAX/b.gz", "KDMX/c", "README"]);
+
+        assert!(products[0].1.as_ref().unwrap().wrappers.is_empty());
+        let gzipped = products[1].1.as_ref().unwrap();
+        assert_eq!(gzipped.wrappers, [Wrapper::Gzip]);
+        assert_eq!(gzipped.station_code, "KOAX");
+        assert_eq!(products[2].1.as_ref().unwrap().station_code, "KDMX");
+        assert!(matches!(
+            products[3].1.as_ref().unwrap_err().without_context(),
+            DiprError::Truncated { .. }
+        ));
+    }
+}
+
+#[test]
+fn archive_filters_products_by_their_headers() {
+    let archive = archive();
+    let start = common::config().capture_time;
+
+    let filter = ArchiveFilter {
+        stations: vec!["koax".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(names(&archive, filter), ["KOAX/a", "KOAX/b.gz"]);
+
+    let filter = ArchiveFilter {
+        product_codes: vec![176],
+        start: Some(start + TimeDelta::minutes(10)),
+        end: Some(start + TimeDelta::minutes(20)),
+        ..Default::default()
+    };
+    assert_eq!(names(&archive, filter), ["KOAX/b.gz"]);
+
+    let filter = ArchiveFilter {
+        product_codes: vec![32],
+        ..Default::default()
+    };
+    assert!(names(&archive, filter).is_empty());
+}
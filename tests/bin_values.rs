@@ -0,0 +1,126 @@
+mod common;
+
+use dipr::{BinValue, GeometryOptions, Measurement, ProductType, inch_per_hour};
+use uom::si::{
+    f32::{Length, Velocity},
+    length::inch,
+};
+
+#[test]
+fn generic_bins_always_hold_values() {
+    let mut dipr = common::config().build().unwrap();
+    let raw = &mut dipr.radials[0].raw_values;
+    raw[0] |= 0x0001_0000;
+    raw[1] = u16::MAX as u32;
+
+    let radial = &dipr.radials[0];
+    for (bin_value, value) in radial.bin_values(dipr.product_type).zip(&radial.values) {
+        assert_eq!(bin_value, BinValue::Value(*value));
+    }
+    assert_eq!(
+        BinValue::from_raw(0x0001_0002, ProductType::PrecipRate),
+        BinValue::Value(ProductType::PrecipRate.decode(2))
+    );
+
+    let num_bins = dipr.radials.iter().map(|r| r.values.len()).sum::<usize>();
+    let options = GeometryOptions {
+        skip_missing: true,
+        ..Default::default()
+    };
+    assert_eq!(dipr.clone().into_bins_iter(false).count(), num_bins);
+    assert_eq!(dipr.into_bins_iter_with(false, &options).count(), num_bins);
+}
+
+#[test]
+fn flagged_digital_bins_are_only_skipped_on_request() {
+    let mut dipr = common::config().build().unwrap();
+    dipr.product_type = ProductType::DigitalReflectivity;
+    for radial in &mut dipr.radials {
+        for (bin_idx, raw) in radial.raw_values.iter_mut().enumerate() {
+            *raw = bin_idx as u32;
+        }
+    }
+    let radial = &dipr.radials[0];
+    let bin_values = radial.bin_values(dipr.product_type).collect::<Vec<_>>();
+    assert_eq!(bin_values[0], BinValue::BelowThreshold);
+    assert_eq!(bin_values[1], BinValue::NoData);
+    assert_eq!(bin_values[2], BinValue::Value(radial.values[2]));
+
+    let num_bins = dipr.radials.iter().map(|r| r.values.len()).sum::<usize>();
+    let options = GeometryOptions {
+        skip_missing: true,
+        ..Default::default()
+    };
+    assert_eq!(dipr.clone().into_bins_iter(false).count(), num_bins);
+    assert_eq!(
+        dipr.into_bins_iter_with(false, &options).count(),
+        num_bins - 2 * 360
+    );
+}
+
+#[test]
+fn digital_accumulation_zeros_are_values() {
+    let mut dipr = common::config().build().unwrap();
+    dipr.product_type = ProductType::DigitalStormTotalAccumulation;
+    let zero = Measurement::Depth(Length::new::<inch>(0.));
+    for radial in &mut dipr.radials {
+        radial.raw_values.fill(0);
+        radial.values.fill(zero);
+    }
+    assert_eq!(
+        BinValue::from_raw(0, dipr.product_type),
+        BinValue::Value(ProductType::DigitalStormTotalAccumulation.decode(0))
+    );
+    assert_eq!(BinValue::from_raw(256, dipr.product_type), BinValue::NoData);
+    assert!(
+        dipr.radials[0]
+            .bin_values(dipr.product_type)
+            .all(|bin_value| bin_value == BinValue::Value(zero))
+    );
+
+    let num_bins = dipr.radials.iter().map(|r| r.values.len()).sum::<usize>();
+    let options = GeometryOptions {
+        skip_missing: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        dipr.clone().into_bins_iter_with(false, &options).count(),
+        num_bins
+    );
+    assert_eq!(dipr.into_bins_iter_with(true, &options).count(), 0);
+}
+
+#[test]
+fn zero_dbz_is_not_skipped_as_zero_precipitation() {
+    let mut dipr = common::config().build().unwrap();
+    dipr.product_type = ProductType::DigitalReflectivity;
+    for radial in &mut dipr.radials {
+        radial.raw_values.fill(66);
+        radial.values.fill(Measurement::Reflectivity(0.));
+    }
+    assert!(!Measurement::Reflectivity(0.).is_no_precipitation());
+
+    let num_bins = dipr.radials.iter().map(|r| r.values.len()).sum::<usize>();
+    assert_eq!(dipr.into_bins_iter(true).count(), num_bins);
+}
+
+#[test]
+fn zero_rates_are_skipped_on_request() {
+    let mut dipr = common::config().build().unwrap();
+    let zero = Measurement::Rate(Velocity::new::<inch_per_hour>(0.));
+    assert!(zero.is_no_precipitation());
+    for radial in &mut dipr.radials {
+        radial.raw_values[0] = 0;
+        radial.values[0] = zero;
+        radial.raw_values[1] = 1;
+        radial.values[1] = ProductType::PrecipRate.decode(1);
+    }
+    let num_zeros = dipr
+        .radials
+        .iter()
+        .flat_map(|r| &r.values)
+        .filter(|value| value.is_no_precipitation())
+        .count();
+    let num_bins = dipr.radials.iter().map(|r| r.values.len()).sum::<usize>();
+    assert_eq!(dipr.into_bins_iter(true).count(), num_bins - num_zeros);
+}
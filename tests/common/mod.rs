@@ -0,0 +1,125 @@
+//! Fixtures shared by the integration tests
+
+#![allow(dead_code)]
+
+use std::io::{Read, Write};
+
+use dipr::{PrecipPattern, SynthConfig, inch_per_hour};
+use flate2::{Compression, write::GzEncoder};
+use uom::si::{
+    f32::{Length, Velocity},
+    length::kilometer,
+};
+
+/// Length of the text header, message header, and product description block
+pub const HEADERS_LENGTH: usize = 150;
+
+/// Offset of the message code in the message header
+const MESSAGE_CODE_OFFSET: usize = 30;
+
+/// Offset of the message length in the message header
+const MESSAGE_LENGTH_OFFSET: usize = 38;
+
+/// Offset of the product code in the product description block
+pub const PRODUCT_CODE_OFFSET: usize = 60;
+
+/// Offset of the data level thresholds (halfwords 31-46) in the product description block
+const THRESHOLDS_OFFSET: usize = 90;
+
+/// Offset of the compression method in the product description block
+pub const COMPRESSION_METHOD_OFFSET: usize = 130;
+
+/// Offset of the uncompressed size in the product description block
+const UNCOMPRESSED_SIZE_OFFSET: usize = 132;
+
+/// Small product with a full sweep of radials and rings of precipitation
+pub fn config() -> SynthConfig {
+    SynthConfig {
+        num_bins: 20,
+        patterns: vec![PrecipPattern::Rings {
+            spacing: Length::new::<kilometer>(2.),
+            peak: Velocity::new::<inch_per_hour>(2.),
+        }],
+        ..Default::default()
+    }
+}
+
+/// Encoded bytes of [`config`]
+pub fn product() -> Vec<u8> {
+    config().encode().unwrap()
+}
+
+/// Wrap `input` in a gzip container
+pub fn gzip(input: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(input).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Rewrite `product` so that its product symbology block is stored uncompressed
+pub fn uncompressed(product: &[u8]) -> Vec<u8> {
+    let (headers, compressed) = product.split_at(HEADERS_LENGTH);
+    let mut symbology = vec![];
+    bzip2::read::BzDecoder::new(compressed)
+        .read_to_end(&mut symbology)
+        .unwrap();
+
+    let mut output = headers.to_vec();
+    let length = (HEADERS_LENGTH - 30 + symbology.len()) as u32;
+    output[MESSAGE_LENGTH_OFFSET..MESSAGE_LENGTH_OFFSET + 4].copy_from_slice(&length.to_be_bytes());
+    output[COMPRESSION_METHOD_OFFSET..COMPRESSION_METHOD_OFFSET + 2].copy_from_slice(&[0, 0]);
+    output.extend(symbology);
+    output
+}
+
+/// Uncompressed digital radial product (packet code 16) with 360 radials that are 1° wide
+///
+/// The headers come from [`product`], with the product code and data level thresholds replaced.
+/// `level` gives the data level of each bin from the index of its radial and its own index.
+pub fn digital(
+    product_code: i16,
+    thresholds: [i16; 16],
+    num_bins: usize,
+    level: impl Fn(usize, usize) -> u8,
+) -> Vec<u8> {
+    let mut packet = vec![];
+    for halfword in [16, 0, num_bins as i16, 0, 0, 999, 360] {
+        packet.extend_from_slice(&i16::to_be_bytes(halfword));
+    }
+    for radial_idx in 0..360 {
+        for halfword in [num_bins as i16, radial_idx as i16 * 10, 10] {
+            packet.extend_from_slice(&i16::to_be_bytes(halfword));
+        }
+        packet.extend((0..num_bins).map(|bin_idx| level(radial_idx, bin_idx)));
+        if num_bins % 2 == 1 {
+            packet.push(0);
+        }
+    }
+
+    let mut symbology = vec![];
+    symbology.extend_from_slice(&(-1i16).to_be_bytes());
+    symbology.extend_from_slice(&1i16.to_be_bytes());
+    symbology.extend_from_slice(&((16 + packet.len()) as u32).to_be_bytes());
+    symbology.extend_from_slice(&1i16.to_be_bytes());
+    symbology.extend_from_slice(&(-1i16).to_be_bytes());
+    symbology.extend_from_slice(&(packet.len() as u32).to_be_bytes());
+    symbology.extend(packet);
+
+    let mut output = product()[..HEADERS_LENGTH].to_vec();
+    output[MESSAGE_CODE_OFFSET..MESSAGE_CODE_OFFSET + 2]
+        .copy_from_slice(&product_code.to_be_bytes());
+    output[PRODUCT_CODE_OFFSET..PRODUCT_CODE_OFFSET + 2]
+        .copy_from_slice(&product_code.to_be_bytes());
+    for (idx, halfword) in thresholds.iter().enumerate() {
+        let offset = THRESHOLDS_OFFSET + 2 * idx;
+        output[offset..offset + 2].copy_from_slice(&halfword.to_be_bytes());
+    }
+    let length = (HEADERS_LENGTH - 30 + symbology.len()) as u32;
+    output[MESSAGE_LENGTH_OFFSET..MESSAGE_LENGTH_OFFSET + 4].copy_from_slice(&length.to_be_bytes());
+    output[COMPRESSION_METHOD_OFFSET..COMPRESSION_METHOD_OFFSET + 2].copy_from_slice(&[0, 0]);
+    let size = symbology.len() as u32;
+    output[UNCOMPRESSED_SIZE_OFFSET..UNCOMPRESSED_SIZE_OFFSET + 4]
+        .copy_from_slice(&size.to_be_bytes());
+    output.extend(symbology);
+    output
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use dipr::{
+    BinValue, DiprError, DiprReader, Measurement, ParseOptions, ProductType, Thresholds,
+    encode_dipr, parse_dipr, parse_dipr_header, parse_dipr_lazy,
+};
+use uom::si::{
+    angle::degree,
+    areal_mass_density::kilogram_per_square_meter,
+    f32::Length,
+    length::{inch, meter},
+};
+
+/// Threshold halfwords of an accumulation product with 2 data levels per hundredth of an inch,
+/// where level 1 stands for zero
+fn accumulation_thresholds() -> [i16; 16] {
+    let mut thresholds = [0; 16];
+    let scale = 2f32.to_bits();
+    let offset = 1f32.to_bits();
+    thresholds[0] = (scale >> 16) as i16;
+    thresholds[1] = scale as i16;
+    thresholds[2] = (offset >> 16) as i16;
+    thresholds[3] = offset as i16;
+    // maximum data level, leading flags, and trailing flags
+    thresholds[5] = 255;
+    thresholds[6] = 1;
+    thresholds[7] = 0;
+    thresholds
+}
+
+#[test]
+fn accumulation_products_decode_scale_and_offset() {
+    for product_type in [
+        ProductType::DigitalAccumulationArray,
+        ProductType::DigitalStormTotalAccumulation,
+        ProductType::DigitalUserSelectableAccumulation,
+    ] {
+        let product = common::digital(
+            product_type.code(),
+            accumulation_thresholds(),
+            920,
+            |_, bin_idx| (bin_idx % 256) as u8,
+        );
+        let dipr = parse_dipr(&product).unwrap();
+        assert_eq!(dipr.product_type, product_type);
+        assert_eq!(
+            dipr.product_description.thresholds(),
+            Some(Thresholds::ScaleOffset {
+                scale: 200.,
+                offset: 1.
+            })
+        );
+        assert_eq!(dipr.bin_size, Length::new::<meter>(250.));
+        assert_eq!(dipr.range_to_first_bin, Length::new::<meter>(125.));
+        assert_eq!(dipr.radials.len(), 360);
+
+        let radial = &dipr.radials[0];
+        assert_eq!(radial.values.len(), 920);
+        let bin_values = radial.bin_values(product_type).collect::<Vec<_>>();
+        // level 0 means no accumulation, even though the offset puts zero at level 1
+        let zero = BinValue::Value(Measurement::Depth(Length::new::<inch>(0.)));
+        assert_eq!(bin_values[0], zero);
+        assert_eq!(bin_values[1], zero);
+        let depth = bin_values[201].value().unwrap().depth().unwrap();
+        assert!((depth.get::<inch>() - 1.).abs() < 1e-6, "{depth:?}");
+    }
+}
+
+#[test]
+fn run_length_encoded_accumulations_are_unsupported() {
+    for code in [169, 171] {
+        assert_eq!(ProductType::from_code(code), None);
+        let product = common::digital(code, accumulation_thresholds(), 920, |_, _| 0);
+        let error = parse_dipr(&product).unwrap_err();
+        assert!(
+            matches!(error.without_context(), DiprError::Unsupported(_)),
+            "{error:?}"
+        );
+    }
+}
+
+/// Offset of the third product dependent halfword (halfword 30) in the product
+const ELEVATION_OFFSET: usize = 88;
+
+fn reflectivity_thresholds() -> [i16; 16] {
+    // -32.0 dBZ in steps of 0.5 dBZ over 256 levels
+    let mut thresholds = [0; 16];
+    thresholds[..3].copy_from_slice(&[-320, 5, 256]);
+    thresholds
+}
+
+#[test]
+fn reflectivity_products_decode_linear_thresholds() {
+    let product = common::digital(32, reflectivity_thresholds(), 230, |_, bin_idx| {
+        bin_idx as u8
+    });
+    let dipr = parse_dipr(&product).unwrap();
+    assert_eq!(dipr.product_type, ProductType::DigitalHybridReflectivity);
+    assert_eq!(dipr.bin_size, Length::new::<meter>(1000.));
+    assert_eq!(dipr.range_to_first_bin, Length::new::<meter>(500.));
+    assert!(dipr.product_type.is_digital_radial());
+
+    let radial = &dipr.radials[10];
+    assert_eq!(radial.azimuth.get::<degree>(), 10.5);
+    assert_eq!(radial.width.get::<degree>(), 1.);
+    assert_eq!(radial.raw_values[..4], [0, 1, 2, 3]);
+    let bin_values = radial.bin_values(dipr.product_type).collect::<Vec<_>>();
+    assert_eq!(bin_values[0], BinValue::BelowThreshold);
+    assert_eq!(bin_values[1], BinValue::NoData);
+    assert_eq!(
+        bin_values[2],
+        BinValue::Value(Measurement::Reflectivity(-32.))
+    );
+    assert_eq!(
+        bin_values[3],
+        BinValue::Value(Measurement::Reflectivity(-31.5))
+    );
+}
+
+#[test]
+fn base_reflectivity_takes_its_elevation_from_the_product_description() {
+    let mut product = common::digital(94, reflectivity_thresholds(), 460, |_, _| 2);
+    product[ELEVATION_OFFSET..ELEVATION_OFFSET + 2].copy_from_slice(&5i16.to_be_bytes());
+    let dipr = parse_dipr(&product).unwrap();
+    assert_eq!(dipr.product_type, ProductType::DigitalReflectivity);
+    assert!(
+        dipr.radials
+            .iter()
+            .all(|r| r.elevation.get::<degree>() == 0.5)
+    );
+}
+
+#[test]
+fn vil_decodes_linear_and_logarithmic_levels() {
+    // linear scale 2 and offset 1 below level 20, then exponential with scale 2 and offset 1
+    let mut thresholds = [0; 16];
+    thresholds[..5].copy_from_slice(&[0x4400, 0x4000, 20, 0x4400, 0x4000]);
+    let product = common::digital(134, thresholds, 230, |_, bin_idx| bin_idx as u8);
+    let dipr = parse_dipr(&product).unwrap();
+    assert_eq!(dipr.product_type, ProductType::DigitalVil);
+
+    let vil = |bin_idx: usize| {
+        let value = dipr.radials[0].values[bin_idx].vil().unwrap();
+        value.get::<kilogram_per_square_meter>()
+    };
+    assert_eq!(vil(5), 2.);
+    assert!((vil(21) - 10f32.exp()).abs() < 1e-2, "{}", vil(21));
+}
+
+#[test]
+fn digital_products_are_only_read_in_full() {
+    let product = common::digital(32, reflectivity_thresholds(), 230, |_, _| 2);
+    let dipr = parse_dipr(&product).unwrap();
+
+    let header = parse_dipr_header(&product, &ParseOptions::default(), false).unwrap();
+    assert_eq!(header.capture_time, Some(dipr.capture_time));
+
+    let error = parse_dipr_lazy(&product, &ParseOptions::default()).unwrap_err();
+    assert!(
+        matches!(error.without_context(), DiprError::Unsupported(_)),
+        "{error:?}"
+    );
+    let error = DiprReader::new(product.as_slice()).err().unwrap();
+    assert!(
+        matches!(error.without_context(), DiprError::Unsupported(_)),
+        "{error:?}"
+    );
+    let error = encode_dipr(&dipr).unwrap_err();
+    assert!(matches!(error, DiprError::Unencodable(_)), "{error:?}");
+}
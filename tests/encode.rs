@@ -0,0 +1,81 @@
+mod common;
+
+use std::io::Read;
+
+use chrono::TimeDelta;
+use dipr::{Component, DiprError, TextComponent, encode_dipr, parse_dipr};
+
+fn text(text: &str) -> Component {
+    Component::Text(TextComponent {
+        parameters: Default::default(),
+        text: text.to_string(),
+    })
+}
+
+#[test]
+fn parsed_product_encodes_byte_for_byte() {
+    let product = common::product();
+    let dipr = parse_dipr(&product).unwrap();
+    assert_eq!(encode_dipr(&dipr).unwrap(), product);
+}
+
+#[test]
+fn encoding_keeps_the_order_of_components() {
+    let mut dipr = common::config().build().unwrap();
+    dipr.components = vec![text("before"), text("after")];
+    dipr.radial_component_index = 1;
+    let product = encode_dipr(&dipr).unwrap();
+
+    let parsed = parse_dipr(&product).unwrap();
+    assert_eq!(parsed.radial_component_index, 1);
+    assert_eq!(parsed.components, dipr.components);
+    assert_eq!(parsed.radials, dipr.radials);
+    assert_eq!(encode_dipr(&parsed).unwrap(), product);
+
+    dipr.radial_component_index = 3;
+    let error = encode_dipr(&dipr).unwrap_err();
+    assert!(matches!(error, DiprError::Unencodable(_)), "{error:?}");
+}
+
+#[test]
+fn uncompressed_symbology_is_encoded_unchanged() {
+    let uncompressed = common::uncompressed(&common::product());
+    let dipr = parse_dipr(&uncompressed).unwrap();
+    let product = encode_dipr(&dipr).unwrap();
+
+    let mut symbology = vec![];
+    bzip2::read::BzDecoder::new(&product[common::HEADERS_LENGTH..])
+        .read_to_end(&mut symbology)
+        .unwrap();
+    assert_eq!(symbology, uncompressed[common::HEADERS_LENGTH..]);
+    let parsed = parse_dipr(&product).unwrap();
+    assert!(parsed.wrappers.is_empty());
+    assert_eq!(parsed.radials, dipr.radials);
+}
+
+#[test]
+fn edited_product_parses_with_the_edits() {
+    let mut dipr = parse_dipr(&common::product()).unwrap();
+    // anonymize the station and shift the scan to a later time
+    dipr.station_code = "ANON".to_string();
+    dipr.capture_time += TimeDelta::hours(1);
+    dipr.scan_number = 2;
+    // trim every radial to its first few bins
+    for radial in &mut dipr.radials {
+        radial.values.truncate(5);
+        radial.raw_values.truncate(5);
+    }
+
+    let parsed = parse_dipr(&encode_dipr(&dipr).unwrap()).unwrap();
+    assert_eq!(parsed.station_code, "ANON");
+    assert_eq!(parsed.text_header.originator, "ANON");
+    assert_eq!(parsed.capture_time, dipr.capture_time);
+    assert_eq!(
+        parsed.description_data.volume_scan_start_time,
+        dipr.capture_time
+    );
+    assert_eq!(parsed.scan_number, 2);
+    assert_eq!(parsed.radials, dipr.radials);
+    assert!(parsed.radials.iter().all(|r| r.values.len() == 5));
+    assert_eq!(parsed.location, dipr.location);
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use dipr::{ArcDensity, Datum, GeometryOptions, PrecipRate};
+use geo::{Coord, Polygon};
+use uom::si::{
+    angle::degree,
+    f32::{Angle, Length},
+    length::kilometer,
+};
+
+/// Product whose radials are `width` degrees wide and follow each other from north, each with one
+/// bin far from the radar station
+fn adjacent_radials(width: f32, num_radials: usize) -> PrecipRate {
+    let mut dipr = common::config().build().unwrap();
+    dipr.range_to_first_bin = Length::new::<kilometer>(200.);
+    let mut radial = dipr.radials[0].clone();
+    radial.values.truncate(1);
+    radial.raw_values.truncate(1);
+    dipr.radials = (0..num_radials)
+        .map(|i| {
+            let mut radial = radial.clone();
+            radial.azimuth = Angle::new::<degree>(width * (i as f32 + 0.5));
+            radial.width = Angle::new::<degree>(width);
+            radial
+        })
+        .collect();
+    dipr
+}
+
+/// Inner and outer vertices at the left and right edges of a single-bin polygon
+fn edges(bin: &Polygon<f64>) -> ([Coord<f64>; 2], [Coord<f64>; 2]) {
+    let ring = &bin.exterior().0;
+    // the ring runs along the inner arc from left to right, then back along the outer arc
+    let num_columns = (ring.len() - 1) / 2;
+    (
+        [ring[0], ring[2 * num_columns - 1]],
+        [ring[num_columns - 1], ring[num_columns]],
+    )
+}
+
+#[test]
+fn adjacent_radials_share_edge_vertices() {
+    for (datum, width) in [Datum::Sphere, Datum::Wgs84]
+        .into_iter()
+        .flat_map(|datum| [0.5, 0.7, 1., 1.3].map(|width| (datum, width)))
+    {
+        let options = GeometryOptions {
+            datum,
+            arc_density: ArcDensity::Segments(3),
+            ..Default::default()
+        };
+        let num_radials = (360. / width) as usize;
+        let bins = adjacent_radials(width, num_radials)
+            .into_bins_iter_with(false, &options)
+            .map(|(bin, _)| edges(&bin.0[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(bins.len(), num_radials);
+        for (i, pair) in bins.windows(2).enumerate() {
+            let ((_, right), (left, _)) = (pair[0], pair[1]);
+            assert_eq!(
+                right, left,
+                "edge after radial {i} of {width}° on the {datum}"
+            );
+        }
+        if num_radials as f32 * width == 360. {
+            let ((left, _), (_, right)) = (bins[0], bins[num_radials - 1]);
+            assert_eq!(
+                right, left,
+                "edge at the end of the sweep of {width}° on the {datum}"
+            );
+        }
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::io::Write;
+
+use dipr::{DiprError, DiprHeader, ParseOptions, Wrapper, parse_dipr, parse_dipr_header};
+use flate2::{Compression, write::GzEncoder};
+
+#[test]
+fn header_matches_parse_dipr() {
+    let product = common::product();
+    let dipr = parse_dipr(&product).unwrap();
+
+    // everything past the headers is ignored unless the capture time is requested
+    let header = parse_dipr_header(
+        &product[..DiprHeader::LENGTH],
+        &ParseOptions::default(),
+        false,
+    )
+    .unwrap();
+    assert_eq!(header.station_code, dipr.station_code);
+    assert_eq!(header.product_type, Some(dipr.product_type));
+    assert_eq!(header.location, dipr.location);
+    assert_eq!(header.operational_mode, dipr.operational_mode);
+    assert_eq!(header.precip_detected, dipr.precip_detected);
+    assert_eq!(header.max_precip_rate, dipr.max_precip_rate);
+    assert_eq!(header.text_header, dipr.text_header);
+    assert_eq!(header.message_header, dipr.message_header);
+    assert_eq!(header.capture_time, None);
+    assert!(header.wrappers.is_empty());
+    assert!(header.warnings.is_empty());
+}
+
+#[test]
+fn header_peeks_at_the_capture_time() {
+    let product = common::product();
+    let dipr = parse_dipr(&product).unwrap();
+    let header = parse_dipr_header(&product, &ParseOptions::default(), true).unwrap();
+    assert_eq!(header.capture_time, Some(dipr.capture_time));
+
+    let uncompressed = common::uncompressed(&product);
+    let header = parse_dipr_header(&uncompressed, &ParseOptions::default(), true).unwrap();
+    assert_eq!(header.capture_time, Some(dipr.capture_time));
+    assert_eq!(header.wrappers, [Wrapper::UncompressedSymbology]);
+}
+
+#[test]
+fn header_unwraps_gzip() {
+    let product = common::product();
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&product).unwrap();
+    let gzipped = encoder.finish().unwrap();
+
+    let header = parse_dipr_header(&gzipped, &ParseOptions::default(), true).unwrap();
+    assert_eq!(header.wrappers, [Wrapper::Gzip]);
+    assert_eq!(
+        header.capture_time,
+        Some(parse_dipr(&product).unwrap().capture_time)
+    );
+}
+
+#[test]
+fn header_accepts_unknown_products() {
+    let mut product = common::product();
+    product[common::PRODUCT_CODE_OFFSET..common::PRODUCT_CODE_OFFSET + 2]
+        .copy_from_slice(&999i16.to_be_bytes());
+    let header = parse_dipr_header(&product, &ParseOptions::default(), false).unwrap();
+    assert_eq!(header.product_type, None);
+    assert!(parse_dipr(&product).is_err());
+}
+
+#[test]
+fn header_reports_truncated_headers() {
+    let product = common::product();
+    for len in 0..DiprHeader::LENGTH {
+        let error =
+            parse_dipr_header(&product[..len], &ParseOptions::default(), false).unwrap_err();
+        let DiprError::Truncated {
+            offset, available, ..
+        } = *error.without_context()
+        else {
+            panic!("prefix of {len} bytes failed with {error:?}");
+        };
+        assert_eq!(offset + available, len);
+    }
+}
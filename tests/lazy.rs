@@ -0,0 +1,56 @@
+mod common;
+
+use dipr::{DiprError, ParseOptions, Radial, parse_dipr, parse_dipr_lazy};
+
+#[test]
+fn lazy_radials_match_parse_dipr() {
+    for product in [common::product(), common::uncompressed(&common::product())] {
+        let dipr = parse_dipr(&product).unwrap();
+        let lazy = parse_dipr_lazy(&product, &ParseOptions::default()).unwrap();
+
+        assert_eq!(lazy.text_header, dipr.text_header);
+        assert_eq!(lazy.wrappers, dipr.wrappers);
+        assert_eq!(lazy.location(), dipr.location);
+        assert_eq!(lazy.bin_size(), dipr.bin_size);
+        assert_eq!(lazy.range_to_first_bin(), dipr.range_to_first_bin);
+        assert_eq!(lazy.num_radials(), dipr.radials.len());
+        let radials = lazy
+            .radials()
+            .map(|r| r.to_radial().unwrap())
+            .collect::<Vec<Radial>>();
+        assert_eq!(radials, dipr.radials);
+    }
+}
+
+#[test]
+fn lazy_radial_views_decode_single_bins() {
+    let product = common::product();
+    let dipr = parse_dipr(&product).unwrap();
+    let lazy = parse_dipr_lazy(&product, &ParseOptions::default()).unwrap();
+
+    let radial = lazy.radial(90).unwrap();
+    let expected = &dipr.radials[90];
+    assert_eq!(radial.azimuth, expected.azimuth);
+    assert_eq!(radial.num_bins(), expected.values.len());
+    for bin_idx in 0..radial.num_bins() {
+        assert_eq!(
+            radial.raw_value(bin_idx),
+            Some(expected.raw_values[bin_idx])
+        );
+        assert_eq!(radial.value(bin_idx), Some(expected.values[bin_idx]));
+    }
+    assert_eq!(radial.value(radial.num_bins()), None);
+    assert!(lazy.radial(lazy.num_radials()).is_none());
+}
+
+#[test]
+fn lazy_parse_reports_truncated_products() {
+    let product = common::product();
+    for len in [100, 151, product.len() - 1] {
+        let error = parse_dipr_lazy(&product[..len], &ParseOptions::default()).unwrap_err();
+        assert!(
+            matches!(error.without_context(), DiprError::Truncated { .. }),
+            "{error:?}"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use dipr::{
+    DiprError, ParseMode, ParseOptions, ParseWarning, Stream, SynthConfig, parse_dipr,
+    parse_dipr_with,
+};
+
+const LENIENT: ParseOptions = ParseOptions {
+    mode: ParseMode::Lenient,
+    max_uncompressed_size: ParseOptions::DEFAULT_MAX_UNCOMPRESSED_SIZE,
+    max_radials: ParseOptions::DEFAULT_MAX_RADIALS,
+    max_bins: ParseOptions::DEFAULT_MAX_BINS,
+};
+
+/// Offset of an XDR string's end given the offset of its length
+fn skip_string(product: &[u8], offset: usize) -> usize {
+    let length = u32::from_be_bytes(product[offset..offset + 4].try_into().unwrap()) as usize;
+    offset + 4 + length.next_multiple_of(4)
+}
+
+/// Uncompressed product whose product description data holds `scan_number`
+fn with_scan_number(scan_number: i32) -> Vec<u8> {
+    let mut product = common::uncompressed(&common::product());
+    // skip the block and packet headers, then every field before the scan number (Figure E-1)
+    let mut offset = common::HEADERS_LENGTH + 24;
+    offset = skip_string(&product, offset);
+    offset = skip_string(&product, offset);
+    offset = skip_string(&product, offset + 12);
+    offset += 24;
+    assert_eq!(product[offset..offset + 4], 1i32.to_be_bytes());
+    product[offset..offset + 4].copy_from_slice(&scan_number.to_be_bytes());
+    product
+}
+
+#[test]
+fn out_of_range_scan_number_is_only_an_error_in_strict_mode() {
+    let product = with_scan_number(90);
+    let error = parse_dipr(&product).unwrap_err();
+    assert!(
+        matches!(error.without_context(), DiprError::ValueOutOfRange(_)),
+        "{error:?}"
+    );
+
+    let dipr = parse_dipr_with(&product, &LENIENT).unwrap();
+    assert_eq!(dipr.scan_number, 90);
+    let [ParseWarning::ValueOutOfRange { context, .. }] = dipr.warnings.as_slice() else {
+        panic!("{:?}", dipr.warnings);
+    };
+    assert_eq!(context.field, Some("scan number"));
+    assert_eq!(context.stream, Stream::Decompressed);
+}
+
+#[test]
+fn scan_number_that_doesnt_fit_is_an_error_in_every_mode() {
+    let product = with_scan_number(300);
+    for options in [ParseOptions::default(), LENIENT] {
+        let error = parse_dipr_with(&product, &options).unwrap_err();
+        let DiprError::Context { context, source } = &error else {
+            panic!("{error:?}");
+        };
+        assert!(
+            matches!(**source, DiprError::ValueOutOfRange(_)),
+            "{error:?}"
+        );
+        assert_eq!(context.field, Some("scan number"));
+        assert_eq!(context.section, "product description data");
+        assert_eq!(context.stream, Stream::Decompressed);
+    }
+}
+
+#[test]
+fn limits_apply_in_every_mode() {
+    let product = common::product();
+    let num_bins = common::config().num_bins;
+    for mode in [ParseMode::Strict, ParseMode::Lenient] {
+        let options = ParseOptions {
+            mode,
+            max_radials: 359,
+            ..Default::default()
+        };
+        let error = parse_dipr_with(&product, &options).unwrap_err();
+        assert!(
+            matches!(
+                error.without_context(),
+                DiprError::LimitExceeded {
+                    limit: 359,
+                    actual: 360,
+                    ..
+                }
+            ),
+            "{error:?}"
+        );
+
+        let options = ParseOptions {
+            mode,
+            max_bins: num_bins - 1,
+            ..Default::default()
+        };
+        let error = parse_dipr_with(&product, &options).unwrap_err();
+        assert!(
+            matches!(error.without_context(), DiprError::LimitExceeded { .. }),
+            "{error:?}"
+        );
+
+        let options = ParseOptions {
+            mode,
+            max_uncompressed_size: 1000,
+            ..Default::default()
+        };
+        let error = parse_dipr_with(&product, &options).unwrap_err();
+        assert!(
+            matches!(
+                error.without_context(),
+                DiprError::PayloadTooLarge { limit: 1000 }
+            ),
+            "{error:?}"
+        );
+    }
+}
+
+#[test]
+fn options_default_to_strict_spec_limits() {
+    let product = SynthConfig::default().encode().unwrap();
+    let dipr = parse_dipr_with(&product, &ParseOptions::default()).unwrap();
+    assert_eq!(dipr, parse_dipr(&product).unwrap());
+    assert!(dipr.warnings.is_empty());
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use std::io::{self, Read};
+
+use dipr::{DiprError, DiprReader, Radial, SynthConfig, Wrapper, parse_dipr};
+
+/// Reader that hands out at most one byte per call, like a slow pipe
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some((first, rest)) = self.0.split_first() else {
+            return Ok(0);
+        };
+        match buf.first_mut() {
+            Some(byte) => *byte = *first,
+            None => return Ok(0),
+        }
+        self.0 = rest;
+        Ok(1)
+    }
+}
+
+fn read_radials(reader: impl Read) -> Result<Vec<Radial>, DiprError> {
+    DiprReader::new(reader)?.collect()
+}
+
+#[test]
+fn reader_yields_the_same_radials_as_parse_dipr() {
+    // full-size radials, so the decompressed stream spans many chunks
+    let product = SynthConfig::default().encode().unwrap();
+    let dipr = parse_dipr(&product).unwrap();
+
+    let mut reader = DiprReader::new(product.as_slice()).unwrap();
+    assert_eq!(reader.text_header(), &dipr.text_header);
+    assert_eq!(reader.message_header(), &dipr.message_header);
+    assert_eq!(reader.num_radials(), dipr.radials.len());
+    assert_eq!(reader.bin_size(), dipr.bin_size);
+    assert_eq!(reader.range_to_first_bin(), dipr.range_to_first_bin);
+    let radials = (&mut reader).collect::<Result<Vec<Radial>, DiprError>>();
+    assert_eq!(radials.unwrap(), dipr.radials);
+    assert!(reader.warnings().is_empty());
+
+    assert_eq!(read_radials(Trickle(&product)).unwrap(), dipr.radials);
+}
+
+#[test]
+fn reader_handles_uncompressed_symbology() {
+    let product = common::uncompressed(&common::product());
+    let dipr = parse_dipr(&product).unwrap();
+
+    let reader = DiprReader::new(product.as_slice()).unwrap();
+    assert_eq!(reader.wrappers(), [Wrapper::UncompressedSymbology]);
+    let radials = reader.collect::<Result<Vec<Radial>, DiprError>>();
+    assert_eq!(radials.unwrap(), dipr.radials);
+    assert_eq!(read_radials(Trickle(&product)).unwrap(), dipr.radials);
+}
+
+#[test]
+fn reader_reports_truncated_radials() {
+    let product = common::uncompressed(&common::product());
+    let error = read_radials(&product[..product.len() - 1]).unwrap_err();
+    assert!(
+        matches!(
+            error.without_context(),
+            DiprError::Truncated {
+                section: "radial",
+                ..
+            }
+        ),
+        "{error:?}"
+    );
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use chrono::TimeDelta;
+use dipr::{DiprError, SynthConfig, encode_dipr, parse_dipr};
+use geo::Point;
+use uom::si::{f32::Length, length::meter};
+
+#[test]
+fn products_at_the_limits_parse_strictly() {
+    let configs = [
+        SynthConfig {
+            num_radials: 180,
+            ..common::config()
+        },
+        SynthConfig {
+            num_radials: 800,
+            num_bins: 2,
+            ..common::config()
+        },
+        SynthConfig {
+            num_radials: 180,
+            num_bins: 1840,
+            bin_size: Length::new::<meter>(1000.),
+            ..common::config()
+        },
+        SynthConfig {
+            range_to_first_bin: Length::new::<meter>(460_000.),
+            scan_number: 80,
+            location: Point::new(180., -90.),
+            ..common::config()
+        },
+    ];
+    for config in configs {
+        let dipr = parse_dipr(&config.encode().unwrap()).unwrap();
+        assert_eq!(dipr.radials, config.build().unwrap().radials);
+    }
+}
+
+#[test]
+fn settings_beyond_the_limits_are_rejected() {
+    let configs = [
+        SynthConfig {
+            num_radials: 179,
+            ..common::config()
+        },
+        SynthConfig {
+            num_radials: 801,
+            ..common::config()
+        },
+        SynthConfig {
+            num_bins: 1841,
+            ..common::config()
+        },
+        SynthConfig {
+            bin_size: Length::new::<meter>(1001.),
+            ..common::config()
+        },
+        SynthConfig {
+            range_to_first_bin: Length::new::<meter>(-1.),
+            ..common::config()
+        },
+        SynthConfig {
+            scan_number: 0,
+            ..common::config()
+        },
+        SynthConfig {
+            scan_number: 81,
+            ..common::config()
+        },
+        SynthConfig {
+            location: Point::new(-96., 90.001),
+            ..common::config()
+        },
+    ];
+    for config in configs {
+        let error = config.build().unwrap_err();
+        assert!(matches!(error, DiprError::Unencodable(_)), "{error:?}");
+        assert!(config.encode().is_err());
+        assert!(config.series(2, TimeDelta::minutes(5)).is_err());
+    }
+}
+
+#[test]
+fn series_advances_time_and_wraps_the_scan_number() {
+    let config = SynthConfig {
+        scan_number: 79,
+        ..common::config()
+    };
+    let products = config
+        .series(3, TimeDelta::minutes(5))
+        .unwrap()
+        .collect::<Vec<_>>();
+    let scan_numbers = products.iter().map(|p| p.scan_number).collect::<Vec<_>>();
+    assert_eq!(scan_numbers, [79, 80, 1]);
+    for (idx, dipr) in products.iter().enumerate() {
+        assert_eq!(
+            dipr.capture_time,
+            config.capture_time + TimeDelta::minutes(5 * idx as i64)
+        );
+        let parsed = parse_dipr(&encode_dipr(dipr).unwrap()).unwrap();
+        assert_eq!(parsed.radials, dipr.radials);
+        assert_eq!(parsed.scan_number, dipr.scan_number);
+    }
+}
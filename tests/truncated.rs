@@ -0,0 +1,97 @@
+mod common;
+
+use dipr::{DiprError, Stream, SynthConfig, parse_dipr};
+
+/// Product with few bins, since every prefix is parsed from scratch
+fn product() -> Vec<u8> {
+    SynthConfig {
+        num_bins: 2,
+        ..common::config()
+    }
+    .encode()
+    .unwrap()
+}
+
+/// Parse every proper prefix of `product` and check that each fails as truncated at a sensible
+/// location, returning the sections that were reported
+fn check_prefixes(product: &[u8]) -> Vec<&'static str> {
+    let mut sections = vec![];
+    for len in 0..product.len() {
+        let error = parse_dipr(&product[..len]).unwrap_err();
+        let DiprError::Context { context, source } = &error else {
+            panic!("prefix of {len} bytes failed without context: {error:?}");
+        };
+        let DiprError::Truncated {
+            section,
+            offset,
+            needed,
+            available,
+        } = **source
+        else {
+            panic!("prefix of {len} bytes failed with {error:?}");
+        };
+
+        let stream_len = match context.stream {
+            Stream::Input => len,
+            Stream::Decompressed => len - common::HEADERS_LENGTH,
+        };
+        assert!(!section.is_empty(), "prefix of {len} bytes: {error:?}");
+        assert_eq!(section, context.section, "prefix of {len} bytes");
+        assert_eq!(offset + available, stream_len, "prefix of {len} bytes");
+        assert!(needed > available, "prefix of {len} bytes: {error:?}");
+        if sections.last() != Some(&section) {
+            sections.push(section);
+        }
+    }
+    parse_dipr(product).unwrap();
+    sections
+}
+
+#[test]
+fn every_prefix_of_a_compressed_product_is_truncated() {
+    let sections = check_prefixes(&product());
+    assert_eq!(
+        sections,
+        [
+            "text header",
+            "message header",
+            "product description block",
+            "product symbology"
+        ]
+    );
+}
+
+#[test]
+fn every_prefix_of_an_uncompressed_product_is_truncated() {
+    let sections = check_prefixes(&common::uncompressed(&product()));
+    assert_eq!(
+        sections,
+        [
+            "text header",
+            "message header",
+            "product description block",
+            "product symbology",
+            "radial component",
+            "radial"
+        ]
+    );
+}
+
+#[test]
+fn truncated_compressed_product_is_located_after_the_headers() {
+    let product = product();
+    let error = parse_dipr(&product[..product.len() - 1]).unwrap_err();
+    let DiprError::Truncated {
+        section,
+        offset,
+        needed,
+        available,
+    } = *error.without_context()
+    else {
+        panic!("{error:?}");
+    };
+    assert_eq!(section, "product symbology");
+    assert_eq!(offset, common::HEADERS_LENGTH);
+    assert_eq!(needed, product.len() - common::HEADERS_LENGTH);
+    assert_eq!(available, needed - 1);
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use dipr::{DiprError, ParseOptions, Wrapper, parse_dipr, parse_dipr_with};
+
+fn noaaport(input: &[u8]) -> Vec<u8> {
+    [b"\x01\r\r\n123 \r\r\n", input, b"\r\r\n\x03"].concat()
+}
+
+#[test]
+fn containers_are_removed_and_recorded() {
+    let product = common::product();
+    let expected = parse_dipr(&product).unwrap();
+    assert!(expected.wrappers.is_empty());
+
+    let (head, tail) = product.split_at(product.len() / 2);
+    let cases = [
+        (common::gzip(&product), vec![Wrapper::Gzip]),
+        (
+            [common::gzip(head), common::gzip(tail)].concat(),
+            vec![Wrapper::Gzip],
+        ),
+        (noaaport(&product), vec![Wrapper::NoaaPort]),
+        (
+            common::gzip(&noaaport(&product)),
+            vec![Wrapper::Gzip, Wrapper::NoaaPort],
+        ),
+    ];
+    for (input, wrappers) in cases {
+        let dipr = parse_dipr(&input).unwrap();
+        assert_eq!(dipr.wrappers, wrappers);
+        assert_eq!(dipr.radials, expected.radials);
+        assert_eq!(dipr.text_header, expected.text_header);
+    }
+}
+
+#[test]
+fn uncompressed_symbology_is_detected_from_the_data() {
+    let product = common::product();
+    let expected = parse_dipr(&product).unwrap();
+
+    let mut uncompressed = common::uncompressed(&product);
+    let dipr = parse_dipr(&uncompressed).unwrap();
+    assert_eq!(dipr.wrappers, [Wrapper::UncompressedSymbology]);
+    assert_eq!(dipr.radials, expected.radials);
+
+    // the compression method still says bzip2, but the block starts with a block divider
+    uncompressed[common::COMPRESSION_METHOD_OFFSET + 1] = 1;
+    let dipr = parse_dipr(&uncompressed).unwrap();
+    assert_eq!(dipr.wrappers, [Wrapper::UncompressedSymbology]);
+    assert_eq!(dipr.radials, expected.radials);
+
+    let dipr = parse_dipr(&common::gzip(&uncompressed)).unwrap();
+    assert_eq!(
+        dipr.wrappers,
+        [Wrapper::Gzip, Wrapper::UncompressedSymbology]
+    );
+}
+
+#[test]
+fn broken_or_oversized_containers_fail() {
+    let product = common::product();
+    let mut corrupt = common::gzip(&product);
+    let len = corrupt.len();
+    corrupt[len - 5] ^= 0xff;
+    let error = parse_dipr(&corrupt).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            DiprError::UnwrapFailed {
+                wrapper: Wrapper::Gzip,
+                ..
+            }
+        ),
+        "{error:?}"
+    );
+
+    let options = ParseOptions {
+        max_uncompressed_size: product.len() - 1,
+        ..Default::default()
+    };
+    let error = parse_dipr_with(&common::gzip(&product), &options).unwrap_err();
+    assert!(
+        matches!(error, DiprError::PayloadTooLarge { .. }),
+        "{error:?}"
+    );
+}